@@ -8,11 +8,20 @@ ldapSearch() {
         "$@"
 }
 
+#
+# Identity
+#
+printf '\n==> Asking who we are bound as\n\n'
+ldapwhoami \
+    -D 'uid=sample_app1,ou=apps,dc=example,dc=com' \
+    -w 'sample_password' \
+    -H 'ldap://127.0.0.1:3389'
+
 #
 # Root
 #
 printf '\n==> Searching root DSE\n\n'
-ldapSearch -b ''
+ldapSearch -b '' -s base '*' '+'
 
 #
 # Groups
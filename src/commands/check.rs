@@ -0,0 +1,51 @@
+use super::{config_paths, load_configuration, read_config_file, ConfigArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct CheckArgs {
+    #[command(flatten)] config: ConfigArgs,
+}
+
+pub async fn main_check(args: CheckArgs) -> anyhow::Result<()> {
+    let mut problem_count = 0;
+
+    // parse every file on its own, to point problems to the file they come from
+    let mut configs = Vec::new();
+    for path in config_paths(&args.config).await? {
+        match read_config_file(&path).await {
+            Ok(config) => configs.push((path, config)),
+            Err(err) => {
+                println!("{}: {err:#}", path.display());
+                problem_count += 1;
+            },
+        }
+    }
+
+    // check the files against the full configuration, as the server would load it
+    if problem_count == 0 {
+        match load_configuration(&args.config).await {
+            Ok(full_config) => {
+                for (path, config) in configs.iter() {
+                    for problem in config.check_items(&full_config) {
+                        println!("{}: {}: {problem}", path.display(), problem.severity);
+                        problem_count += 1;
+                    }
+                }
+                for problem in full_config.check_entries() {
+                    println!("entries: {}: {problem}", problem.severity);
+                    problem_count += 1;
+                }
+            },
+            Err(err) => {
+                println!("configuration: {err:#}");
+                problem_count += 1;
+            },
+        }
+    }
+
+    if problem_count == 0 {
+        println!("Configuration is valid");
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Found {problem_count} problems in the configuration"))
+    }
+}
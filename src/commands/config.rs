@@ -0,0 +1,54 @@
+use crate::ldap::{config::{overlay::CredentialsOverlay, Config}, traits::Mergeable};
+
+#[derive(clap::Args, Debug)]
+pub struct ConfigArgs {
+    /// Path to the config file
+    #[arg(short, long, default_value = "config.toml", env)] pub config_file: std::path::PathBuf,
+
+    /// Path to a configuration directory with multiple configs in it
+    #[arg(long, env)] pub config_dir: Option<std::path::PathBuf>,
+
+    /// Path to the machine-managed file storing passwords changed by clients, enables password changes
+    #[arg(long, env)] pub credentials_overlay_file: Option<std::path::PathBuf>,
+}
+
+/// The config files in load order, the main config file first
+pub async fn config_paths(args: &ConfigArgs) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    let mut extra_paths = Vec::new();
+    if let Some(config_dir) = &args.config_dir {
+        let mut dir_reader = tokio::fs::read_dir(config_dir).await?;
+        while let Some(entry) = dir_reader.next_entry().await? {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if ! file_name.ends_with(".toml") || file_name.starts_with('.') {
+                continue;
+            }
+            if entry.path() == args.config_file || Some(entry.path()) == args.credentials_overlay_file {
+                continue;
+            }
+            extra_paths.push(entry.path());
+        }
+    }
+    extra_paths.sort();
+    Ok(std::iter::once(args.config_file.clone()).chain(extra_paths).collect())
+}
+
+/// Parse a single config file
+pub async fn read_config_file(path: &std::path::Path) -> anyhow::Result<Config> {
+    Ok(toml::from_str::<Config>(&tokio::fs::read_to_string(path).await?)?)
+}
+
+#[tracing::instrument(skip_all, level = tracing::Level::DEBUG, ret)]
+pub async fn load_configuration(args: &ConfigArgs) -> anyhow::Result<Config> {
+    let mut config = read_config_file(&args.config_file).await?;
+    for path in config_paths(args).await?.into_iter().skip(1) {
+        let extra_config = read_config_file(&path).await?;
+        config.merge(extra_config);
+        tracing::debug!(name = ?path, "Loaded extra config file");
+    }
+    if let Some(credentials_overlay_file) = &args.credentials_overlay_file {
+        CredentialsOverlay::load(credentials_overlay_file).await?.apply(&mut config);
+        tracing::debug!(name = ?credentials_overlay_file, "Applied credentials overlay");
+    }
+    config.check_passwords()?;
+    Ok(config)
+}
@@ -1 +1,6 @@
-mod server; pub use server::{main_server, ServerArgs};
\ No newline at end of file
+mod check; pub use check::{main_check, CheckArgs};
+mod config; pub use config::{config_paths, load_configuration, read_config_file, ConfigArgs};
+mod password; pub use password::{main_hash_password, main_verify_password, HashPasswordArgs, VerifyPasswordArgs};
+mod server; pub use server::{main_server, ServerArgs};
+mod tls; pub use tls::{load_tls_config, ReloadableCertificate};
+mod watcher; pub use watcher::FileWatcher;
\ No newline at end of file
@@ -0,0 +1,71 @@
+use std::io::IsTerminal;
+
+use crate::ldap::{datatypes::CIString, password::PasswordScheme};
+
+use super::{load_configuration, ConfigArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct HashPasswordArgs {
+    /// Hashing scheme
+    #[arg(short, long, default_value = "ARGON2")] scheme: PasswordScheme,
+
+    /// Hashing cost (bcrypt cost, Argon2 iterations, crypt and PBKDF2 rounds)
+    #[arg(long)] cost: Option<u32>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyPasswordArgs {
+    #[command(flatten)] config: ConfigArgs,
+
+    /// DN or uid of the entity
+    identity: String,
+}
+
+/// Read a secret from a TTY prompt, or from the first line of stdin
+fn read_secret(prompt: &str) -> anyhow::Result<String> {
+    let secret = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(prompt)?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if secret.is_empty() {
+        return Err(anyhow::anyhow!("Empty password"));
+    }
+    Ok(secret)
+}
+
+pub async fn main_hash_password(args: HashPasswordArgs) -> anyhow::Result<()> {
+    let password = read_secret("Password: ")?;
+    if std::io::stdin().is_terminal() && read_secret("Confirm password: ")? != password {
+        return Err(anyhow::anyhow!("Passwords do not match"));
+    }
+    println!("{}", args.scheme.hash_with_cost(&password, args.cost)?);
+    Ok(())
+}
+
+pub async fn main_verify_password(args: VerifyPasswordArgs) -> anyhow::Result<()> {
+    // find the entity
+    let config = load_configuration(&args.config).await?;
+    let entries = config.assemble_entries();
+    let mut candidates = entries.iter()
+        .filter(|entry| entry.can_perform_bind())
+        .filter(|entry| entry.dn.to_string() == args.identity || entry.attributes.get_attribute(&CIString::new("uid"))
+            .is_some_and(|uid| uid.values.iter().any(|value| value == args.identity.as_bytes())));
+    let Some(entry) = candidates.next() else {
+        return Err(anyhow::anyhow!("No entity with credentials matches {}", args.identity));
+    };
+    if let Some(other) = candidates.next() {
+        return Err(anyhow::anyhow!("Both {} and {} match {}, use a DN", entry.dn, other.dn, args.identity));
+    }
+
+    // check the password as binds do
+    let password = read_secret(&format!("Password for {}: ", entry.dn))?;
+    if entry.attributes.check_password(&password) {
+        println!("Password matches {}", entry.dn);
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Password does not match {}", entry.dn))
+    }
+}
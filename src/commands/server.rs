@@ -1,19 +1,22 @@
 use std::sync::Arc;
 
-use metrics::{counter, describe_counter};
+use metrics::{counter, describe_counter, describe_gauge, gauge};
 use metrics_exporter_prometheus::PrometheusBuilder;
-use rustls::pki_types::pem::PemObject;
 use tokio_util::sync::CancellationToken;
 
-use crate::ldap::{config::Config, database::LDAPReadOnlyInMemoryDatabase, server::ClientHandler, traits::Mergeable};
+use crate::ldap::{config::Config, connection::LDAPConnection, database::LDAPReadOnlyInMemoryDatabase, diff::LDAPDatabaseDiff, schema::format_generalized_time, server::{ClientHandler, ClientHandlerSettings, CredentialsStore, SearchLimits}};
+
+use super::{load_configuration, load_tls_config, ConfigArgs, FileWatcher, ReloadableCertificate};
 
 #[derive(clap::Args, Debug)]
 pub struct ServerArgs {
-    /// Path to the config file
-    #[arg(short, long, default_value = "config.toml", env)] config_file: std::path::PathBuf,
+    #[command(flatten)] config: ConfigArgs,
+
+    /// Reload the database when the config file or the config directory change, and the TLS certificate when its files change
+    #[arg(long, env)] watch_config: bool,
 
-    /// Path to a configuration directory with multiple configs in it
-    #[arg(long, env)] config_dir: Option<std::path::PathBuf>,
+    /// Time in milliseconds without further changes before reloading a changed configuration
+    #[arg(long, default_value_t = 1000, env)] watch_config_debounce_ms: u64,
 
     /// Bind address for the LDAP socket
     #[arg(long, default_value = "0.0.0.0:3389", env)] ldap_bind_addr: Option<String>,
@@ -21,21 +24,52 @@ pub struct ServerArgs {
     /// Bind address for the LDAPS socket
     #[arg(long, requires = "ldaps_certificate_file", requires = "ldaps_key_file", env)] ldaps_bind_addr: Option<String>,
 
-    /// TLS certificate file path
-    #[arg(long, env)] ldaps_certificate_file: Option<std::path::PathBuf>,
+    /// TLS certificate file path, used for LDAPS and StartTLS
+    #[arg(long, requires = "ldaps_key_file", env)] ldaps_certificate_file: Option<std::path::PathBuf>,
+
+    /// TLS key file path, used for LDAPS and StartTLS
+    #[arg(long, requires = "ldaps_certificate_file", env)] ldaps_key_file: Option<std::path::PathBuf>,
 
-    /// TLS key file path
-    #[arg(long, env)] ldaps_key_file: Option<std::path::PathBuf>,
+    /// Refuse binds and searches on the LDAP socket until StartTLS is negotiated
+    #[arg(long, requires = "ldaps_certificate_file", env)] require_tls: bool,
 
     /// Prometheus metrics endpoint
     #[arg(long, default_value = "127.0.0.1:9000", env)] metrics_bind_addr: Option<String>,
+
+    /// Maximum number of entries returned by a search of an app account (0 for no limit)
+    #[arg(long, default_value_t = 0, env)] apps_search_size_limit: usize,
+
+    /// Maximum duration in seconds of a search of an app account (0 for no limit)
+    #[arg(long, default_value_t = 0, env)] apps_search_time_limit: u64,
+
+    /// Maximum number of entries returned by a search of a user (0 for no limit)
+    #[arg(long, default_value_t = 0, env)] users_search_size_limit: usize,
+
+    /// Maximum duration in seconds of a search of a user (0 for no limit)
+    #[arg(long, default_value_t = 0, env)] users_search_time_limit: u64,
+}
+impl ServerArgs {
+    fn client_handler_settings(&self, tls_config: Option<Arc<rustls::ServerConfig>>, credentials_store: Option<Arc<CredentialsStore>>) -> ClientHandlerSettings {
+        ClientHandlerSettings {
+            privileged_search_limits: SearchLimits {
+                size_limit: self.apps_search_size_limit,
+                time_limit: std::time::Duration::from_secs(self.apps_search_time_limit),
+            },
+            search_limits: SearchLimits {
+                size_limit: self.users_search_size_limit,
+                time_limit: std::time::Duration::from_secs(self.users_search_time_limit),
+            },
+            tls_config,
+            require_tls: self.require_tls,
+            credentials_store,
+        }
+    }
 }
 
 enum LDAPListenerType {
     Plain,
     Tls {
-        cert_path: std::path::PathBuf,
-        key_path: std::path::PathBuf,
+        tls_config: Arc<rustls::ServerConfig>,
     }
 }
 impl LDAPListenerType {
@@ -51,35 +85,17 @@ impl LDAPListenerType {
 async fn listen(
     addr: impl tokio::net::ToSocketAddrs,
     mut database_rx: tokio::sync::watch::Receiver<Arc<LDAPReadOnlyInMemoryDatabase>>,
+    settings: Arc<ClientHandlerSettings>,
     cancellation_token: CancellationToken,
     listener_type: LDAPListenerType,
 ) -> anyhow::Result<()> {
-    // get initial database instance
-    let mut database = database_rx.borrow().clone();
-
     // prepare task joinset
     let mut tasks = tokio::task::JoinSet::new();
 
     // create TLS acceptor
     let tls_acceptor = match &listener_type {
         LDAPListenerType::Plain => None,
-        LDAPListenerType::Tls { cert_path, key_path } => {
-            // parse certificate and key material
-            let tls_cert = tokio::fs::read(cert_path).await?;
-            let tls_cert = rustls::pki_types::CertificateDer::from_pem_slice(&tls_cert)?;
-            let tls_key = tokio::fs::read(key_path).await?;
-            let tls_key = rustls::pki_types::PrivateKeyDer::from_pem_slice(&tls_key)?;
-
-            // prepare TLS config
-            let tls_config = rustls::ServerConfig::builder_with_protocol_versions(&[&rustls::version::TLS13])
-                .with_no_client_auth()
-                .with_single_cert(vec![tls_cert], tls_key)?;
-
-            // accept TLS connections
-            let tls_acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(tls_config));
-
-            Some(tls_acceptor)
-        }
+        LDAPListenerType::Tls { tls_config } => Some(tokio_rustls::TlsAcceptor::from(tls_config.clone())),
     };
 
     // start listening
@@ -94,8 +110,7 @@ async fn listen(
                 break;
             }
             r = database_rx.changed() => {
-                let _ = r?;
-                database = database_rx.borrow().clone();
+                r?;
                 tracing::info!("Reloaded database");
             }
             r = tasks.join_next(), if !tasks.is_empty() => {
@@ -109,7 +124,8 @@ async fn listen(
                 match r {
                     Ok((conn, addr)) => {
                         let connection_counter = counter!("femtoldap_connections_total", "protocol" => listener_type.protocol_name());
-                        let database = database.clone();
+                        let database_rx = database_rx.clone();
+                        let settings = settings.clone();
                         let tls_acceptor = tls_acceptor.clone();
                         tasks.spawn(async move {
                             if let Some(tls_acceptor) = tls_acceptor {
@@ -117,12 +133,12 @@ async fn listen(
                                 let conn = tls_acceptor.accept(conn).await
                                     .inspect_err(|err| tracing::error!(error = ?err, "Error in accepting TLS connection"))?;
                                 connection_counter.increment(1);
-                                let mut handler = ClientHandler::new(conn, addr, database);
+                                let mut handler = ClientHandler::new(LDAPConnection::Tls(Box::new(conn)), addr, database_rx, settings);
                                 handler.handle_connection().await
                             } else {
                                 // serve over plain TCP
                                 connection_counter.increment(1);
-                                let mut handler = ClientHandler::new(conn, addr, database);
+                                let mut handler = ClientHandler::new(LDAPConnection::Plain(conn), addr, database_rx, settings);
                                 handler.handle_connection().await
                             }
                         });
@@ -146,50 +162,127 @@ async fn listen(
     Ok(())
 }
 
+/// Swap in the current certificate and key, the previous ones are kept being served on failure
+async fn reload_certificate(certificate: &ReloadableCertificate) {
+    match certificate.reload().await {
+        Ok(()) => tracing::info!("Loaded new TLS certificate"),
+        Err(error) => tracing::error!(?error, "Failed reloading TLS certificate, keeping the previous one"),
+    }
+}
+
+/// A generation of the database, counting reloads
+#[derive(Debug, Clone, Copy)]
+struct DatabaseGeneration {
+    number: u64,
+    loaded_at: std::time::SystemTime,
+}
+impl DatabaseGeneration {
+    fn next(self) -> Self {
+        Self { number: self.number + 1, loaded_at: std::time::SystemTime::now() }
+    }
+
+    fn record_metrics(self) {
+        gauge!("femtoldap_reload_generation").set(self.number as f64);
+        gauge!("femtoldap_reload_timestamp").set(self.loaded_at.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs_f64());
+    }
+}
+
 #[tracing::instrument(skip_all, level = tracing::Level::DEBUG, ret)]
-async fn load_configuration(args: &ServerArgs) -> anyhow::Result<Config> {
-    let mut config = toml::from_str::<Config>(&tokio::fs::read_to_string(&args.config_file).await?)?;
-    if let Some(config_dir) = &args.config_dir {
-        let mut dir_reader = tokio::fs::read_dir(config_dir).await?;
-        while let Some(entry) = dir_reader.next_entry().await? {
-            if ! entry.file_name().to_string_lossy().ends_with(".toml") {
-                continue;
-            }
-            if entry.path() == args.config_file {
-                continue;
-            }
-            let extra_config = toml::from_str::<Config>(&tokio::fs::read_to_string(&entry.path()).await?)?;
-            config.merge(extra_config);
-            tracing::debug!(name = ?entry.path(), "Loaded extra config file");
+async fn create_database_from_config(args: &ServerArgs, config: &Config, generation: DatabaseGeneration) -> anyhow::Result<Arc<LDAPReadOnlyInMemoryDatabase>> {
+    let mut entries = config.assemble_entries();
+    if let Some(root_dse) = entries.iter_mut().find(|entry| entry.dn.is_empty()) {
+        // extensions depending on the server settings
+        if args.ldaps_certificate_file.is_some() {
+            root_dse.attributes.add_value("supportedExtension", "1.3.6.1.4.1.1466.20037"); // RFC 4511 - StartTLS
         }
+        if args.config.credentials_overlay_file.is_some() {
+            root_dse.attributes.add_value("supportedExtension", ldap3_proto::proto::OID_PASSWORD_MODIFY); // RFC 3062 - Password Modify
+        }
+        root_dse.attributes.add_value("femtoldapReloadGeneration", generation.number.to_string());
+        root_dse.attributes.add_value("femtoldapReloadTimestamp", format_generalized_time(generation.loaded_at));
     }
-    Ok(config)
+    Ok(LDAPReadOnlyInMemoryDatabase::from_entries(entries)?.into())
 }
 
-#[tracing::instrument(skip_all, level = tracing::Level::DEBUG, ret)]
-async fn create_database_from_config(config: &Config) -> Arc<LDAPReadOnlyInMemoryDatabase> {
-    LDAPReadOnlyInMemoryDatabase::from_entries(config.assemble_entries()).into()
+/// Swap in a database built from the current configuration, the previous one is kept being served on failure
+async fn reload_database(args: &ServerArgs, database_tx: &tokio::sync::watch::Sender<Arc<LDAPReadOnlyInMemoryDatabase>>, generation: &mut DatabaseGeneration) -> anyhow::Result<()> {
+    let next_generation = generation.next();
+    let result = async {
+        // load configuration
+        let config = load_configuration(&args.config).await?;
+        config.validate()?;
+        // load database
+        let database = create_database_from_config(args, &config, next_generation).await?;
+        // report changes
+        let diff = LDAPDatabaseDiff::new(&database_tx.borrow(), &database);
+        if diff.is_empty() {
+            tracing::info!(generation = next_generation.number, "No entries changed");
+        }
+        for dn in diff.added.iter() {
+            tracing::info!(generation = next_generation.number, %dn, "Entry added");
+        }
+        for dn in diff.removed.iter() {
+            tracing::info!(generation = next_generation.number, %dn, "Entry removed");
+        }
+        for (dn, attribute_diffs) in diff.modified.iter() {
+            let changes = attribute_diffs.iter().map(|attribute_diff| attribute_diff.to_string()).collect::<Vec<_>>();
+            tracing::info!(generation = next_generation.number, %dn, ?changes, "Entry modified");
+        }
+        // update config
+        database_tx.send(database)?;
+        anyhow::Ok(())
+    }.await;
+    match result {
+        Ok(()) => {
+            *generation = next_generation;
+            generation.record_metrics();
+        },
+        Err(_) => counter!("femtoldap_reload_failures_total").increment(1),
+    }
+    result
 }
 
 pub async fn main_server(args: ServerArgs) -> anyhow::Result<()> {
     // load configuration
-    let config = load_configuration(&args).await?;
+    let config = load_configuration(&args.config).await?;
+    config.validate()?;
 
     // load database
-    let database = create_database_from_config(&config).await;
+    let mut generation = DatabaseGeneration { number: 1, loaded_at: std::time::SystemTime::now() };
+    let database = create_database_from_config(&args, &config, generation).await?;
 
     // database watch channel
     let (database_tx, database_rx) = tokio::sync::watch::channel(database.clone());
 
+    // load TLS config
+    let (tls_config, certificate) = match (&args.ldaps_certificate_file, &args.ldaps_key_file) {
+        (Some(tls_cert_path), Some(tls_key_path)) => {
+            let (tls_config, certificate) = load_tls_config(tls_cert_path, tls_key_path).await?;
+            (Some(tls_config), Some(certificate))
+        },
+        _ => (None, None),
+    };
+
+    // credentials store
+    let (reload_tx, mut reload_rx) = tokio::sync::mpsc::channel::<tokio::sync::oneshot::Sender<()>>(1);
+    let credentials_store = args.config.credentials_overlay_file.clone().map(|overlay_path| Arc::new(CredentialsStore {
+        overlay_path,
+        overlay_lock: tokio::sync::Mutex::new(()),
+        reload_tx: reload_tx.clone(),
+    }));
+
+    // client handler settings
+    let settings = Arc::new(args.client_handler_settings(tls_config.clone(), credentials_store));
+
     // spawn tasks
     let cancellation_token = CancellationToken::new();
     let mut tasks = tokio::task::JoinSet::new();
 
     if let Some(addr) = &args.ldap_bind_addr {
-        tasks.spawn(listen(addr.clone(), database_rx.clone(), cancellation_token.clone(), LDAPListenerType::Plain));
+        tasks.spawn(listen(addr.clone(), database_rx.clone(), settings.clone(), cancellation_token.clone(), LDAPListenerType::Plain));
     }
-    if let (Some(addr), Some(tls_cert_path), Some(tls_key_path)) = (&args.ldaps_bind_addr, &args.ldaps_certificate_file, &args.ldaps_key_file) {
-        tasks.spawn(listen(addr.clone(), database_rx.clone(), cancellation_token.clone(), LDAPListenerType::Tls { cert_path: tls_cert_path.clone(), key_path: tls_key_path.clone() }));
+    if let (Some(addr), Some(tls_config)) = (&args.ldaps_bind_addr, &tls_config) {
+        tasks.spawn(listen(addr.clone(), database_rx.clone(), settings.clone(), cancellation_token.clone(), LDAPListenerType::Tls { tls_config: tls_config.clone() }));
     }
     if let Some(addr) = &args.metrics_bind_addr {
         PrometheusBuilder::new()
@@ -201,8 +294,24 @@ pub async fn main_server(args: ServerArgs) -> anyhow::Result<()> {
         describe_counter!("femtoldap_successful_binds_total", metrics::Unit::Count, "Total number of successful bind requests");
         describe_counter!("femtoldap_failed_binds_total", metrics::Unit::Count, "Total number of failed bind requests");
         describe_counter!("femtoldap_requests_total", metrics::Unit::Count, "Total number of requests");
+        describe_counter!("femtoldap_reload_failures_total", metrics::Unit::Count, "Total number of failed database reloads");
+        describe_gauge!("femtoldap_reload_generation", metrics::Unit::Count, "Generation of the database being served, incremented on every reload");
+        describe_gauge!("femtoldap_reload_timestamp", metrics::Unit::Seconds, "Time at which the database being served was loaded");
+        generation.record_metrics();
     }
 
+    // configuration and certificate watchers
+    let watch_debounce = std::time::Duration::from_millis(args.watch_config_debounce_ms);
+    let mut config_watcher = if args.watch_config {
+        Some(FileWatcher::for_config(&args.config, watch_debounce)?)
+    } else {
+        None
+    };
+    let mut certificate_watcher = match &certificate {
+        Some(certificate) if args.watch_config => Some(FileWatcher::new(&certificate.paths(), None, watch_debounce)?),
+        _ => None,
+    };
+
     // wait for signal
     let mut sighup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
     let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
@@ -211,13 +320,44 @@ pub async fn main_server(args: ServerArgs) -> anyhow::Result<()> {
         tokio::select! {
             _ = sighup.recv() => {
                 tracing::info!("Starting database reload");
-                // load configuration
-                let config = load_configuration(&args).await?;
-                // load database
-                let database = create_database_from_config(&config).await;
-                // update config
-                database_tx.send(database)?;
-                tracing::info!("Loaded new database");
+                match reload_database(&args, &database_tx, &mut generation).await {
+                    Ok(()) => tracing::info!("Loaded new database"),
+                    Err(error) => tracing::error!(?error, "Failed reloading database, keeping the previous one"),
+                }
+                if let Some(certificate) = &certificate {
+                    reload_certificate(certificate).await;
+                }
+            }
+            r = async { config_watcher.as_mut().expect("No configuration watcher").changed().await }, if config_watcher.is_some() => {
+                if let Err(error) = r {
+                    tracing::error!(?error, "Configuration watcher stopped, reload with SIGHUP instead");
+                    config_watcher = None;
+                    continue;
+                }
+                tracing::info!("Starting database reload after configuration change");
+                match reload_database(&args, &database_tx, &mut generation).await {
+                    Ok(()) => tracing::info!("Loaded new database"),
+                    Err(error) => tracing::error!(?error, "Failed reloading database, keeping the previous one"),
+                }
+            }
+            r = async { certificate_watcher.as_mut().expect("No certificate watcher").changed().await }, if certificate_watcher.is_some() => {
+                if let Err(error) = r {
+                    tracing::error!(?error, "Certificate watcher stopped, reload with SIGHUP instead");
+                    certificate_watcher = None;
+                    continue;
+                }
+                tracing::info!("Starting TLS certificate reload after file change");
+                reload_certificate(certificate.as_ref().expect("No certificate")).await;
+            }
+            Some(done_tx) = reload_rx.recv() => {
+                tracing::info!("Starting database reload after credentials change");
+                match reload_database(&args, &database_tx, &mut generation).await {
+                    Ok(()) => {
+                        tracing::info!("Loaded new database");
+                        let _ = done_tx.send(());
+                    },
+                    Err(error) => tracing::error!(?error, "Failed reloading database, keeping the previous one"),
+                }
             }
             _ = sigterm.recv() => {
                 cancellation_token.cancel();
@@ -251,3 +391,69 @@ pub async fn main_server(args: ServerArgs) -> anyhow::Result<()> {
     // done
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use clap::Parser;
+
+    use super::{create_database_from_config, reload_database, DatabaseGeneration, ServerArgs};
+
+    #[derive(clap::Parser)]
+    struct TestCli {
+        #[command(flatten)] args: ServerArgs,
+    }
+
+    #[tokio::test]
+    async fn test_reload_database() {
+        let dir = std::env::temp_dir().join(format!("femtoldap-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("config.toml");
+        std::fs::write(&config_file, r#"
+            base_dn = "dc=example,dc=com"
+
+            [[users]]
+            uid = "user1"
+        "#).unwrap();
+        let args = TestCli::parse_from(["femtoldap", "--config-file", config_file.to_str().unwrap()]).args;
+
+        // the same validation runs at startup and on reload
+        let config = crate::commands::load_configuration(&args.config).await.unwrap();
+        config.validate().unwrap();
+        let mut generation = DatabaseGeneration { number: 1, loaded_at: std::time::SystemTime::now() };
+        let database = create_database_from_config(&args, &config, generation).await.unwrap();
+        let (database_tx, database_rx) = tokio::sync::watch::channel(database);
+
+        // warnings, such as a skipped group, don't prevent reloading
+        std::fs::write(&config_file, r#"
+            base_dn = "dc=example,dc=com"
+
+            [[groups]]
+            name = "empty"
+
+            [[users]]
+            uid = "user1"
+            group_names = [ "unknown" ]
+        "#).unwrap();
+        reload_database(&args, &database_tx, &mut generation).await.unwrap();
+        assert_eq!(generation.number, 2);
+
+        // errors keep the previous database
+        let previous = database_rx.borrow().clone();
+        std::fs::write(&config_file, r#"
+            base_dn = "dc=example,dc=com"
+
+            [[users]]
+            uid = "user1"
+
+            [[users]]
+            uid = "USER1"
+        "#).unwrap();
+        assert!(reload_database(&args, &database_tx, &mut generation).await.is_err());
+        assert_eq!(generation.number, 2);
+        assert!(Arc::ptr_eq(&previous, &database_rx.borrow()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject;
+
+/// Certificate and key served over TLS, swapped on reloads while established connections keep theirs
+#[derive(Debug)]
+pub struct ReloadableCertificate {
+    cert_path: std::path::PathBuf,
+    key_path: std::path::PathBuf,
+    crypto_provider: Arc<rustls::crypto::CryptoProvider>,
+    certified_key: std::sync::RwLock<Arc<rustls::sign::CertifiedKey>>,
+}
+impl ReloadableCertificate {
+
+    async fn load(cert_path: &std::path::Path, key_path: &std::path::Path, crypto_provider: Arc<rustls::crypto::CryptoProvider>) -> anyhow::Result<Self> {
+        let certified_key = load_certified_key(cert_path, key_path, &crypto_provider).await?;
+        Ok(Self {
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+            crypto_provider,
+            certified_key: std::sync::RwLock::new(certified_key),
+        })
+    }
+
+    /// Read the certificate and key again, the current ones are kept on failure
+    #[tracing::instrument(skip_all)]
+    pub async fn reload(&self) -> anyhow::Result<()> {
+        let certified_key = load_certified_key(&self.cert_path, &self.key_path, &self.crypto_provider).await?;
+        *self.certified_key.write().expect("Poisoned certificate lock") = certified_key;
+        Ok(())
+    }
+
+    /// The files the certificate and key are read from
+    pub fn paths(&self) -> [std::path::PathBuf; 2] {
+        [self.cert_path.clone(), self.key_path.clone()]
+    }
+
+}
+impl rustls::server::ResolvesServerCert for ReloadableCertificate {
+    fn resolve(&self, _client_hello: rustls::server::ClientHello<'_>) -> Option<Arc<rustls::sign::CertifiedKey>> {
+        Some(self.certified_key.read().expect("Poisoned certificate lock").clone())
+    }
+}
+
+async fn load_certified_key(cert_path: &std::path::Path, key_path: &std::path::Path, crypto_provider: &rustls::crypto::CryptoProvider) -> anyhow::Result<Arc<rustls::sign::CertifiedKey>> {
+    // parse certificate chain and key material
+    let tls_certs = tokio::fs::read(cert_path).await?;
+    let tls_certs = rustls::pki_types::CertificateDer::pem_slice_iter(&tls_certs).collect::<Result<Vec<_>, _>>()?;
+    if tls_certs.is_empty() {
+        return Err(anyhow::anyhow!("No certificate found in {cert_path:?}"));
+    }
+    let tls_key = tokio::fs::read(key_path).await?;
+    let tls_key = rustls::pki_types::PrivateKeyDer::from_pem_slice(&tls_key)?;
+
+    // check they belong together
+    Ok(Arc::new(rustls::sign::CertifiedKey::from_der(tls_certs, tls_key, crypto_provider)?))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn load_tls_config(cert_path: &std::path::Path, key_path: &std::path::Path) -> anyhow::Result<(Arc<rustls::ServerConfig>, Arc<ReloadableCertificate>)> {
+    let tls_config_builder = rustls::ServerConfig::builder_with_protocol_versions(&[&rustls::version::TLS13]);
+
+    // load certificate and key
+    let certificate = Arc::new(ReloadableCertificate::load(cert_path, key_path, tls_config_builder.crypto_provider().clone()).await?);
+
+    // prepare TLS config
+    let tls_config = tls_config_builder
+        .with_no_client_auth()
+        .with_cert_resolver(certificate.clone());
+
+    Ok((Arc::new(tls_config), certificate))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::ldap::server::tests::{TEST_CERTIFICATE, TEST_KEY};
+
+    use super::load_tls_config;
+
+    #[tokio::test]
+    async fn test_certificate_reload() {
+        let dir = std::env::temp_dir().join(format!("femtoldap-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path) = (dir.join("tls.crt"), dir.join("tls.key"));
+        std::fs::write(&cert_path, TEST_CERTIFICATE).unwrap();
+        std::fs::write(&key_path, TEST_KEY).unwrap();
+
+        let (_tls_config, certificate) = load_tls_config(&cert_path, &key_path).await.unwrap();
+        let current = || certificate.certified_key.read().unwrap().clone();
+        let loaded = current();
+
+        // broken material is not picked up
+        std::fs::write(&cert_path, "garbage").unwrap();
+        assert!(certificate.reload().await.is_err());
+        assert!(Arc::ptr_eq(&current(), &loaded));
+
+        // renewed material is
+        std::fs::write(&cert_path, TEST_CERTIFICATE).unwrap();
+        certificate.reload().await.unwrap();
+        assert!(!Arc::ptr_eq(&current(), &loaded));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
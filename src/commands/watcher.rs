@@ -0,0 +1,117 @@
+use notify::{event::ModifyKind, EventKind, Watcher};
+
+use super::ConfigArgs;
+
+/// Watches files for changes, including the symlink swaps of Kubernetes volumes
+pub struct FileWatcher {
+    _watcher: notify::RecommendedWatcher,
+    events_rx: tokio::sync::mpsc::UnboundedReceiver<()>,
+    debounce: std::time::Duration,
+}
+impl FileWatcher {
+
+    /// Watch the config file and the config directory
+    pub fn for_config(args: &ConfigArgs, debounce: std::time::Duration) -> anyhow::Result<Self> {
+        Self::new(std::slice::from_ref(&args.config_file), args.config_dir.as_deref(), debounce)
+    }
+
+    /// Watch some files, and the visible `.toml` files of a directory
+    pub fn new(files: &[std::path::PathBuf], dir: Option<&std::path::Path>, debounce: std::time::Duration) -> anyhow::Result<Self> {
+        let files = files.iter().map(std::path::absolute).collect::<Result<Vec<_>, _>>()?;
+        let dir = dir.map(std::path::absolute).transpose()?;
+
+        // files of interest, kubernetes swaps the `..data` symlink to update all of the files at once
+        let is_relevant = {
+            let files = files.clone();
+            let dir = dir.clone();
+            move |path: &std::path::Path| {
+                files.iter().any(|file| path == file)
+                    || path.file_name().is_some_and(|name| name.to_string_lossy().starts_with(".."))
+                    || dir.as_deref().is_some_and(|dir| path.parent() == Some(dir) && path.extension().is_some_and(|ext| ext == "toml")
+                        && !path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')))
+            }
+        };
+
+        // forward events, reading files is not a change
+        let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) => {
+                let is_change = matches!(
+                    event.kind,
+                    EventKind::Any | EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Any | ModifyKind::Data(_) | ModifyKind::Name(_)),
+                );
+                if is_change && event.paths.iter().any(|path| is_relevant(path)) {
+                    tracing::debug!(?event, "Watched files changed");
+                    let _ = events_tx.send(());
+                }
+            },
+            Err(error) => tracing::error!(?error, "Error while watching files"),
+        })?;
+
+        // watch the directories, as files get replaced rather than modified
+        let mut watched_dirs = files.iter()
+            .map(|file| file.parent().unwrap_or(std::path::Path::new("/")).to_path_buf())
+            .chain(dir)
+            .collect::<Vec<_>>();
+        watched_dirs.sort();
+        watched_dirs.dedup();
+        for watched_dir in watched_dirs.iter() {
+            watcher.watch(watched_dir, notify::RecursiveMode::NonRecursive)?;
+            tracing::info!(path = ?watched_dir, "Watching directory");
+        }
+
+        Ok(Self { _watcher: watcher, events_rx, debounce })
+    }
+
+    /// Wait for a change, returning once no further changes happened for the debounce duration
+    pub async fn changed(&mut self) -> anyhow::Result<()> {
+        self.events_rx.recv().await.ok_or_else(|| anyhow::anyhow!("File watcher stopped"))?;
+        loop {
+            match tokio::time::timeout(self.debounce, self.events_rx.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) => return Err(anyhow::anyhow!("File watcher stopped")),
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::commands::ConfigArgs;
+
+    use super::FileWatcher;
+
+    #[tokio::test]
+    async fn test_file_watcher() {
+        // lay out a volume as kubernetes does
+        let volume = std::env::temp_dir().join(format!("femtoldap-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(volume.join("..v1")).unwrap();
+        std::fs::create_dir_all(volume.join("..v2")).unwrap();
+        std::fs::write(volume.join("..v1/config.toml"), "base_dn = \"dc=example,dc=com\"").unwrap();
+        std::fs::write(volume.join("..v2/config.toml"), "base_dn = \"dc=example,dc=org\"").unwrap();
+        std::os::unix::fs::symlink("..v1", volume.join("..data")).unwrap();
+        std::os::unix::fs::symlink("..data/config.toml", volume.join("config.toml")).unwrap();
+        std::fs::write(volume.join("unrelated.txt"), "").unwrap();
+
+        let args = ConfigArgs { config_file: volume.join("config.toml"), config_dir: None, credentials_overlay_file: None };
+        let mut watcher = FileWatcher::for_config(&args, Duration::from_millis(50)).unwrap();
+
+        // unrelated files and reads are ignored
+        std::fs::write(volume.join("unrelated.txt"), "changed").unwrap();
+        std::fs::read_to_string(volume.join("config.toml")).unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(200), watcher.changed()).await.is_err());
+
+        // swap the data symlink atomically
+        std::os::unix::fs::symlink("..v2", volume.join("..data_tmp")).unwrap();
+        std::fs::rename(volume.join("..data_tmp"), volume.join("..data")).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), watcher.changed()).await.unwrap().unwrap();
+        assert!(std::fs::read_to_string(volume.join("config.toml")).unwrap().contains("dc=org"));
+
+        std::fs::remove_dir_all(&volume).unwrap();
+    }
+
+}
@@ -5,16 +5,15 @@ pub struct LDAPACL {
     pub can_access_self: bool,
     #[serde(default)] pub can_access_suffixes: Vec<LDAPDN>, // TODO - ACL simplifier
     #[serde(default)] pub cant_access_suffixes: Vec<LDAPDN>, // TODO - ACL simplifier
+    #[serde(default)] pub can_reset_passwords: bool,
 }
 impl LDAPACL {
 
     pub fn can_access_dn(&self, entry: &LDAPEntry, target_dn: &LDAPDN) -> bool {
         if self.can_access_self && (&entry.dn == target_dn) {
             true
-        } else if self.can_access_suffixes.iter().any(|suffix| target_dn.matches_suffix(suffix)) && !self.cant_access_suffixes.iter().any(|suffix| target_dn.matches_suffix(suffix)) {
-            true
         } else {
-            false
+            self.can_access_suffixes.iter().any(|suffix| target_dn.matches_suffix(suffix)) && !self.cant_access_suffixes.iter().any(|suffix| target_dn.matches_suffix(suffix))
         }
     }
 
@@ -29,5 +28,6 @@ impl Mergeable<Self> for LDAPACL {
         self.can_access_self.merge(other.can_access_self);
         self.can_access_suffixes.merge(other.can_access_suffixes);
         self.cant_access_suffixes.merge(other.cant_access_suffixes);
+        self.can_reset_passwords.merge(other.can_reset_passwords);
     }
 }
@@ -1,6 +1,6 @@
 use std::fmt::Write;
 
-use super::traits::Mergeable;
+use super::{datatypes::CIString, schema, traits::Mergeable};
 
 #[derive(Clone, Default, PartialEq, Eq, Hash)]
 pub struct LDAPAttribute {
@@ -43,7 +43,7 @@ impl std::fmt::Debug for LDAPAttribute {
             if i != 0 {
                 f.write_str(", ")?;
             }
-            if let Ok(s) = std::str::from_utf8(&value) {
+            if let Ok(s) = std::str::from_utf8(value) {
                 s.fmt(f)?;
             } else {
                 value.fmt(f)?;
@@ -59,3 +59,53 @@ impl Mergeable<Self> for LDAPAttribute {
         self.values.merge(other.values);
     }
 }
+
+/// An attribute type together with its options, e.g. `cn;lang-en` (RFC 4512 section 2.5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeDescription {
+    pub name: CIString,
+    pub options: Vec<CIString>,
+}
+impl AttributeDescription {
+
+    pub fn parse(s: &str) -> Self {
+        let mut components = s.split(';');
+        Self {
+            name: CIString::new(components.next().unwrap_or_default()),
+            options: components
+                .filter(|option| !option.is_empty())
+                .map(CIString::new)
+                .collect(),
+        }
+    }
+
+    /// Whether the binary transfer option is set (RFC 4522)
+    pub fn is_binary(&self) -> bool {
+        self.options.contains(&CIString::new("binary"))
+    }
+
+    /// Whether this requested description selects an attribute with the given description,
+    /// i.e. they refer to the same type and the attribute has all the requested options
+    pub fn selects(&self, attribute: &AttributeDescription) -> bool {
+        let attribute_type = schema::attribute_type(&self.name);
+        let same_type = self.name == attribute.name || (!attribute_type.names.is_empty() && attribute_type.has_name(&attribute.name));
+        same_type
+            &&
+        self.options.iter()
+            .filter(|option| **option != CIString::new("binary"))
+            .all(|option| attribute.options.iter().any(|attribute_option| Self::option_selects(option, attribute_option)))
+    }
+
+    /// Match options exactly, except language ranges ending with `-` which match their subtags (RFC 3866 section 3.1)
+    fn option_selects(requested: &CIString, option: &CIString) -> bool {
+        let requested = requested.to_string().to_lowercase();
+        let option = option.to_string().to_lowercase();
+        if requested.starts_with("lang-") {
+            requested == option
+                || (requested.ends_with('-') && option.starts_with(&requested))
+        } else {
+            requested == option
+        }
+    }
+
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use super::{attribute::LDAPAttribute, datatypes::CIString, traits::Mergeable};
+use super::{attribute::{AttributeDescription, LDAPAttribute}, datatypes::CIString, schema, traits::Mergeable};
 
 #[derive(Clone, Default, PartialEq, Eq)]
 pub struct LDAPAttributes {
@@ -29,7 +29,8 @@ impl LDAPAttributes {
     pub fn check_password(&self, password: impl AsRef<str>) -> bool {
         if let Some(attribute) = self.get_attribute(&CIString::new("userPassword")) {
             attribute.values.iter()
-                .any(|accepted_password| accepted_password == password.as_ref().as_bytes())
+                .filter_map(|accepted_password| std::str::from_utf8(accepted_password).ok())
+                .any(|accepted_password| super::password::verify_password(accepted_password, password.as_ref()))
         } else {
             false
         }
@@ -47,6 +48,33 @@ impl LDAPAttributes {
         self.attributes.values()
     }
 
+    /// Get the attributes selected by a search request
+    pub fn select(&self, selection: &AttributeSelection, types_only: bool) -> Vec<ldap3_proto::LdapPartialAttribute> {
+        self.attributes.values()
+            .filter_map(|attribute| {
+                let description = AttributeDescription::parse(&attribute.name);
+                let operational = schema::attribute_type(&description.name).operational;
+
+                // explicitly requested
+                let requested = selection.descriptions.iter()
+                    .filter(|requested| requested.selects(&description))
+                    .collect::<Vec<_>>();
+                if requested.is_empty() && !(if operational { selection.all_operational } else { selection.all_user }) {
+                    return None;
+                }
+
+                let mut partial_attribute = attribute.as_ldap3_protocol_attribute();
+                if requested.iter().any(|requested| requested.is_binary()) && !description.is_binary() {
+                    partial_attribute.atype.push_str(";binary");
+                }
+                if types_only {
+                    partial_attribute.vals.clear();
+                }
+                Some(partial_attribute)
+            })
+            .collect()
+    }
+
     pub fn tidy(&mut self) {
         // delete empty attributes
         self.attributes.retain(|_, v| !v.is_empty());
@@ -57,6 +85,37 @@ impl LDAPAttributes {
     }
 
 }
+/// The attributes requested by a search (RFC 4511 section 4.5.1.8 and RFC 3673)
+#[derive(Debug, Clone, Default)]
+pub struct AttributeSelection {
+    all_user: bool,
+    all_operational: bool,
+    descriptions: Vec<AttributeDescription>,
+}
+impl AttributeSelection {
+
+    pub fn new(requested: &[String]) -> Self {
+        let mut selection = Self::default();
+        for attribute in requested.iter().map(|x| x.trim()) {
+            match attribute {
+                // all user attributes
+                "*" => selection.all_user = true,
+                // all operational attributes
+                "+" => selection.all_operational = true,
+                // no attributes, only meaningful when alone
+                "1.1" | "" => {},
+                attribute => selection.descriptions.push(AttributeDescription::parse(attribute)),
+            }
+        }
+        // an empty list requests all user attributes
+        if requested.is_empty() {
+            selection.all_user = true;
+        }
+        selection
+    }
+
+}
+
 impl<I: AsRef<str>> std::ops::Index<I> for LDAPAttributes {
     type Output = LDAPAttribute;
     fn index(&self, index: I) -> &Self::Output {
@@ -86,3 +145,46 @@ impl Mergeable<Self> for LDAPAttributes {
         self.attributes.merge(other.attributes);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AttributeSelection, LDAPAttributes};
+
+    fn select(requested: &[&str], types_only: bool) -> Vec<(String, usize)> {
+        let mut attributes = LDAPAttributes::default();
+        attributes.add_value("objectClass", "inetOrgPerson");
+        attributes.add_value("givenName", "Sample");
+        attributes.add_value("cn", "Sample");
+        attributes.add_value("cn;lang-en-us", "Sample (US)");
+        attributes.add_value("entryUUID", "6ba7b810-9dad-11d1-80b4-00c04fd430c8");
+        let requested = requested.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let mut selected = attributes.select(&AttributeSelection::new(&requested), types_only)
+            .into_iter()
+            .map(|attribute| (attribute.atype, attribute.vals.len()))
+            .collect::<Vec<_>>();
+        selected.sort_unstable();
+        selected
+    }
+
+    #[test]
+    fn test_attribute_selection() {
+        let user_attributes = vec![
+            ("cn".to_string(), 1),
+            ("cn;lang-en-us".to_string(), 1),
+            ("givenName".to_string(), 1),
+            ("objectClass".to_string(), 1),
+        ];
+        assert_eq!(select(&[], false), user_attributes);
+        assert_eq!(select(&["*"], false), user_attributes);
+        assert_eq!(select(&["1.1"], false), vec![]);
+        assert_eq!(select(&["+"], false), vec![("entryUUID".to_string(), 1)]);
+        assert_eq!(select(&["givenname", "ENTRYUUID"], false), vec![("entryUUID".to_string(), 1), ("givenName".to_string(), 1)]);
+        assert_eq!(select(&["givenName"], true), vec![("givenName".to_string(), 0)]);
+        assert_eq!(select(&["commonName;lang-en-US"], false), vec![("cn;lang-en-us".to_string(), 1)]);
+        assert_eq!(select(&["commonName;lang-en"], false), vec![]);
+        assert_eq!(select(&["commonName;lang-en-"], false), vec![("cn;lang-en-us".to_string(), 1)]);
+        assert_eq!(select(&["cn;lang-"], false), vec![("cn;lang-en-us".to_string(), 1)]);
+        assert_eq!(select(&["givenName;binary"], false), vec![("givenName;binary".to_string(), 1)]);
+    }
+
+}
@@ -0,0 +1,339 @@
+use std::{collections::HashMap, str::FromStr};
+
+use crate::ldap::{attributes::LDAPAttributes, config::{layout::{is_plain_rdn_value, EntityKind}, traits::AsLDAPAttributes}, datatypes::CIString, dn::LDAPDN, schema};
+
+use super::{items::{Group, GroupObjectClass}, traits::AugmentConfig, Config};
+
+/// How serious a problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Some entry is left out or incomplete, the server still serves the configuration
+    Warning,
+    /// The server refuses to serve the configuration
+    Error,
+}
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem found in the configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// The item the problem was found in
+    pub item: String,
+    pub message: String,
+    pub severity: Severity,
+}
+impl ConfigProblem {
+    fn warning(item: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { item: item.into(), message: message.into(), severity: Severity::Warning }
+    }
+    fn error(item: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { item: item.into(), message: message.into(), severity: Severity::Error }
+    }
+}
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.item, self.message)
+    }
+}
+
+impl Config {
+
+    /// Check the items of this configuration, resolving references and DNs against the full configuration
+    pub fn check_items(&self, full_config: &Config) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        // the full configuration as the entries are assembled from it
+        let mut resolved_config = full_config.clone();
+        let cyclic_member_groups = Group::break_cycles(&mut resolved_config.data.groups);
+
+        // applications
+        let apps_dns = self.data.apps.iter().map(|app| app.as_ldap_dn(full_config)).collect::<Vec<_>>();
+        for (i, app) in self.data.apps.iter().enumerate() {
+            let item = format!("apps[{i}]");
+            if app.uid.is_none() {
+                problems.push(ConfigProblem::warning(&item, "missing uid"));
+            }
+            if app.auth.password.is_none() {
+                problems.push(ConfigProblem::warning(&item, "missing password, the app cannot bind"));
+            }
+        }
+        check_rdns(&mut problems, "apps", full_config, EntityKind::App, self.data.apps.iter().map(|app| app.as_ldap_attributes()));
+        check_dns(&mut problems, "apps", &apps_dns);
+
+        // containers
+        let containers_dns = self.data.containers.iter().map(|container| container.as_ldap_dn(full_config)).collect::<Vec<_>>();
+        for (i, container) in self.data.containers.iter().enumerate() {
+            if container.dn.as_ref().is_none_or(|dn| dn.is_empty()) {
+                problems.push(ConfigProblem::warning(format!("containers[{i}]"), "missing dn"));
+            }
+        }
+        check_dns(&mut problems, "containers", &containers_dns);
+
+        // groups
+        let groups_dns = self.data.groups.iter().map(|group| group.as_ldap_dn(full_config)).collect::<Vec<_>>();
+        for (i, group) in self.data.groups.iter().enumerate() {
+            let item = format!("groups[{i}]");
+            if group.name.is_none() {
+                problems.push(ConfigProblem::warning(&item, "missing name"));
+            }
+            if let Some(gid_number) = group.gid_number.as_ref() {
+                if gid_number.parse::<u32>().is_err() {
+                    problems.push(ConfigProblem::warning(&item, format!("bad gid number {gid_number}")));
+                }
+                if let Some(j) = self.data.groups[..i].iter().position(|other| other.gid_number.as_ref() == Some(gid_number)) {
+                    problems.push(ConfigProblem::warning(&item, format!("duplicate gid number {gid_number}, also used by groups[{j}]")));
+                }
+            }
+            if group.gid_number.is_none() && group.object_classes.contains(&GroupObjectClass::PosixGroup) {
+                problems.push(ConfigProblem::warning(&item, "posixGroup without a gid number"));
+            }
+            let mut member_group_names = group.member_groups.iter().collect::<Vec<_>>();
+            member_group_names.sort();
+            for member_group_name in member_group_names {
+                if !full_config.data.groups.iter().any(|other| other.name.as_ref() == Some(member_group_name)) {
+                    problems.push(ConfigProblem::warning(&item, format!("unknown member group {member_group_name}")));
+                } else if cyclic_member_groups.iter().any(|(cyclic_group_name, cyclic_member_group_name)| group.name.as_ref() == Some(cyclic_group_name) && cyclic_member_group_name == member_group_name) {
+                    problems.push(ConfigProblem::warning(&item, format!("member group {member_group_name} closes a membership cycle, it is dropped from the group")));
+                }
+            }
+            if let Some(resolved_group) = resolved_config.data.groups.iter().find(|other| group.name.is_some() && other.name == group.name) && !resolved_group.is_emitted(&resolved_config) {
+                problems.push(ConfigProblem::warning(&item, "no members for its object classes, the group is skipped"));
+            }
+        }
+        check_rdns(&mut problems, "groups", full_config, EntityKind::Group, self.data.groups.iter().map(|group| group.as_ldap_attributes()));
+        check_dns(&mut problems, "groups", &groups_dns);
+
+        // mail aliases
+        let mail_aliases_dns = self.data.mail_aliases.iter().map(|mail_alias| mail_alias.as_ldap_dn(full_config)).collect::<Vec<_>>();
+        for (i, mail_alias) in self.data.mail_aliases.iter().enumerate() {
+            if mail_alias.mail.is_none() {
+                problems.push(ConfigProblem::warning(format!("mail_aliases[{i}]"), "missing mail"));
+            }
+        }
+        check_rdns(&mut problems, "mail_aliases", full_config, EntityKind::MailAlias, self.data.mail_aliases.iter().map(|mail_alias| mail_alias.as_ldap_attributes()));
+        check_dns(&mut problems, "mail_aliases", &mail_aliases_dns);
+
+        // users
+        let users_dns = self.data.users.iter().map(|user| user.as_ldap_dn(full_config)).collect::<Vec<_>>();
+        for (i, user) in self.data.users.iter().enumerate() {
+            let item = format!("users[{i}]");
+            if user.uid.is_none() {
+                problems.push(ConfigProblem::warning(&item, "missing uid"));
+            }
+            for group_name in user.group_names.iter() {
+                if !full_config.data.groups.iter().any(|group| group.name.as_ref() == Some(group_name)) {
+                    problems.push(ConfigProblem::warning(&item, format!("unknown group {group_name}")));
+                }
+            }
+            for mail_alias in user.mail_aliases.iter() {
+                if !full_config.data.mail_aliases.iter().any(|other| other.mail.as_ref() == Some(mail_alias)) {
+                    problems.push(ConfigProblem::warning(&item, format!("unknown mail alias {mail_alias}")));
+                }
+            }
+            if !user.mail_aliases.is_empty() && user.mail.is_none() {
+                problems.push(ConfigProblem::warning(&item, "mail aliases without a mail address"));
+            }
+        }
+        check_rdns(&mut problems, "users", full_config, EntityKind::User, self.data.users.iter().map(|user| user.layout_attributes(full_config)));
+        check_dns(&mut problems, "users", &users_dns);
+
+        problems
+    }
+
+    /// Check the configuration before serving it, logging every problem and failing on errors
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let problems = self.check_items(self).into_iter()
+            .chain(self.check_entries())
+            .collect::<Vec<_>>();
+        for problem in problems.iter() {
+            match problem.severity {
+                Severity::Warning => tracing::warn!(%problem, "Configuration problem"),
+                Severity::Error => tracing::error!(%problem, "Configuration problem"),
+            }
+        }
+        let error_count = problems.iter().filter(|problem| problem.severity == Severity::Error).count();
+        if error_count == 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Found {error_count} errors in the configuration"))
+        }
+    }
+
+    /// Check the entries assembled from the configuration
+    pub fn check_entries(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        // every entry the server would load, including the synthesized base and container entries
+        let entries = self.assemble_entries();
+        let mut dns = HashMap::<LDAPDN, &LDAPDN>::new();
+        let mut uuids = HashMap::<uuid::Uuid, &LDAPDN>::new();
+        for entry in entries.iter() {
+            // distinguished names, compared as the server does
+            if let Some(other_dn) = dns.insert(schema::normalize_dn(&entry.dn), &entry.dn) {
+                if *other_dn == entry.dn {
+                    problems.push(ConfigProblem::error(entry.dn.to_string(), "duplicate DN"));
+                } else {
+                    problems.push(ConfigProblem::error(entry.dn.to_string(), format!("duplicate DN, also used by {other_dn}")));
+                }
+                continue;
+            }
+
+            // unique identifiers
+            let Some(attribute) = entry.attributes.get_attribute(&CIString::new("entryUUID")) else {
+                continue;
+            };
+            for value in attribute.values.iter() {
+                match std::str::from_utf8(value).ok().and_then(|value| uuid::Uuid::parse_str(value).ok()) {
+                    Some(uuid) => if let Some(other_dn) = uuids.insert(uuid, &entry.dn) {
+                        problems.push(ConfigProblem::error(entry.dn.to_string(), format!("UUID {uuid} is also used by {other_dn}")));
+                    },
+                    None => problems.push(ConfigProblem::error(entry.dn.to_string(), format!("invalid UUID {}", String::from_utf8_lossy(value)))),
+                }
+            }
+        }
+
+        problems
+    }
+
+}
+
+/// Check that the values naming the entities can be used in their DN
+fn check_rdns(problems: &mut Vec<ConfigProblem>, kind_name: &str, config: &Config, kind: EntityKind, attributes: impl Iterator<Item = LDAPAttributes>) {
+    let rdn_attribute = config.layout.rdn_attribute(kind);
+    for (i, attributes) in attributes.enumerate() {
+        if let Some(rdn_value) = config.layout.rdn_value(kind, &attributes).filter(|rdn_value| !is_plain_rdn_value(rdn_value)) {
+            problems.push(ConfigProblem::error(format!("{kind_name}[{i}]"), format!("{rdn_attribute} {rdn_value:?} can't be used in a DN without escaping")));
+        }
+    }
+}
+
+/// Check that DNs are well formed and unique
+fn check_dns(problems: &mut Vec<ConfigProblem>, kind: &str, dns: &[Option<LDAPDN>]) {
+    for (i, dn) in dns.iter().enumerate() {
+        let Some(dn) = dn else {
+            continue;
+        };
+        if LDAPDN::from_str(&dn.to_string()).ok().as_ref() != Some(dn) {
+            problems.push(ConfigProblem::error(format!("{kind}[{i}]"), format!("bad DN {dn}")));
+        }
+        if let Some(j) = dns[..i].iter().position(|other| other.as_ref() == Some(dn)) {
+            problems.push(ConfigProblem::error(format!("{kind}[{i}]"), format!("duplicate DN {dn}, also used by {kind}[{j}]")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ldap::{config::{fixtures, Config}, traits::Mergeable};
+
+    #[test]
+    fn test_check() {
+        let config = fixtures::config(r#"
+            [[apps]]
+            uid = "app"
+
+            [[groups]]
+            description = "nameless"
+
+            [[groups]]
+            name = "posix1"
+            gid_number = "1000"
+
+            [[groups]]
+            name = "posix2"
+            gid_number = "1000"
+
+            [[groups]]
+            name = "posix3"
+            gid_number = "-1"
+            member_groups = [ "posix4", "unknown" ]
+
+            [[groups]]
+            name = "posix4"
+            member_groups = [ "posix3" ]
+            object_classes = [ "groupOfNames", "posixGroup" ]
+
+            [[users]]
+            uid = "user1"
+            group_names = [ "missing" ]
+            mail_aliases = [ "alias@example.com" ]
+
+            [[users]]
+            uid = "user1"
+
+            [[users]]
+            uid = "user,2"
+
+            [[users]]
+            uid = "user3"
+            uuid = "8c5b2a5e-7a4c-4d8e-9f3b-2b8f5a6c1d2e"
+
+            [[users]]
+            uid = "user4"
+            uuid = "8c5b2a5e-7a4c-4d8e-9f3b-2b8f5a6c1d2e"
+
+            [[containers]]
+            dn = "UID=user4,ou=users,dc=example,dc=com"
+        "#);
+
+        let problems = config.check_items(&config).into_iter()
+            .chain(config.check_entries())
+            .map(|problem| problem.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(problems, vec![
+            "apps[0]: missing password, the app cannot bind",
+            "groups[0]: missing name",
+            "groups[2]: duplicate gid number 1000, also used by groups[1]",
+            "groups[3]: bad gid number -1",
+            "groups[3]: unknown member group unknown",
+            "groups[4]: posixGroup without a gid number",
+            "groups[4]: member group posix3 closes a membership cycle, it is dropped from the group",
+            "groups[4]: no members for its object classes, the group is skipped",
+            "users[0]: unknown group missing",
+            "users[0]: unknown mail alias alias@example.com",
+            "users[0]: mail aliases without a mail address",
+            "users[2]: uid \"user,2\" can't be used in a DN without escaping",
+            "users[1]: duplicate DN uid=user1,ou=users,dc=example,dc=com, also used by users[0]",
+            "uid=user1,ou=users,dc=example,dc=com: duplicate DN",
+            "uid=user4,ou=users,dc=example,dc=com: UUID 8c5b2a5e-7a4c-4d8e-9f3b-2b8f5a6c1d2e is also used by uid=user3,ou=users,dc=example,dc=com",
+            "UID=user4,ou=users,dc=example,dc=com: duplicate DN, also used by uid=user4,ou=users,dc=example,dc=com",
+        ]);
+    }
+
+    #[test]
+    fn test_check_split_files() {
+        let settings = fixtures::config(r#"
+            [layout.users]
+            rdn_attribute = "cn"
+        "#);
+        let users = toml::from_str::<Config>(r#"
+            base_dn = ""
+
+            [[users]]
+            uid = "user1"
+            display_name = "Same Name"
+
+            [[users]]
+            uid = "user2"
+            display_name = "Same Name"
+        "#).unwrap();
+        let mut full_config = settings.clone();
+        full_config.merge(users.clone());
+
+        // DNs follow the layout and base DN of the full configuration
+        let problems = users.check_items(&full_config).into_iter()
+            .map(|problem| problem.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(problems, vec![
+            "users[1]: duplicate DN cn=Same Name,ou=users,dc=example,dc=com, also used by users[0]",
+        ]);
+    }
+
+}
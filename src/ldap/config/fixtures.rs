@@ -0,0 +1,18 @@
+use crate::ldap::{datatypes::CIString, entry::LDAPEntry};
+
+use super::Config;
+
+/// Parse the items of a configuration under `dc=example,dc=com`
+pub fn config(items: &str) -> Config {
+    toml::from_str::<Config>(&format!("base_dn = \"dc=example,dc=com\"\n{items}")).unwrap()
+}
+
+/// The sorted values of an attribute of an entry, empty when the attribute is missing
+pub fn attributes(entries: &[LDAPEntry], dn: &str, name: &str) -> Vec<String> {
+    let entry = entries.iter().find(|entry| entry.dn.to_string() == dn).unwrap();
+    let mut values = entry.attributes.get_attribute(&CIString::new(name)).map(|attribute| attribute.values.iter()
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+        .collect::<Vec<_>>()).unwrap_or_default();
+    values.sort();
+    values
+}
@@ -1,4 +1,4 @@
-use crate::ldap::{attributes::LDAPAttributes, config::traits::{AsLDAPAttributes, AugmentConfig}, entry::LDAPEntry, traits::Mergeable};
+use crate::ldap::{attributes::LDAPAttributes, config::{layout::EntityKind, traits::{AsLDAPAttributes, AugmentConfig, NaturalKey}}, entry::LDAPEntry, traits::Mergeable};
 
 use super::{ExtraProperties, LoginProperties};
 
@@ -8,6 +8,9 @@ pub struct AppAccount {
     /// Authentication data
     #[serde(flatten)] pub auth: LoginProperties,
 
+    /// Whether the app may reset the passwords of the entries it can access
+    #[serde(default)] pub can_reset_passwords: bool,
+
     /// Description
     pub description: Option<String>,
 
@@ -33,34 +36,36 @@ impl AsLDAPAttributes for AppAccount {
     }
 }
 impl AugmentConfig for AppAccount {
-    fn as_ldap_dn(&self, base_dn: &crate::ldap::dn::LDAPDN) -> Option<crate::ldap::dn::LDAPDN> {
-        self.uid.as_ref()
-            .map(|name|
-                base_dn.clone()
-                    .with_prefix("ou", "apps")
-                    .with_prefix("uid", name)
-                )
+    fn as_ldap_dn(&self, config: &crate::ldap::config::Config) -> Option<crate::ldap::dn::LDAPDN> {
+        config.layout.dn(EntityKind::App, &config.base_dn, &self.as_ldap_attributes())
     }
     fn as_ldap_entry(&self, config: &crate::ldap::config::Config, _entries: &[LDAPEntry]) -> Option<LDAPEntry> {
         if self.uid.is_none() {
             tracing::warn!("Entry skipped: missing uid");
             return None;
         }
-        let mut entry = LDAPEntry::new(self.as_ldap_dn(&config.base_dn)?, self.as_ldap_attributes());
+        let mut entry = LDAPEntry::new(self.as_ldap_dn(config)?, self.as_ldap_attributes());
+        entry.kind = Some(EntityKind::App);
         entry.acls.can_access_self = true;
+        entry.acls.can_reset_passwords = self.can_reset_passwords;
         entry.acls.can_access_suffixes.push(
             config.base_dn.clone()
         );
         entry.acls.cant_access_suffixes.push(
-            config.base_dn.clone()
-                .with_prefix("ou", "apps")
+            config.layout.container_dn(EntityKind::App, &config.base_dn)
         );
         Some(entry)
     }
 }
+impl NaturalKey for AppAccount {
+    fn natural_key(&self) -> Option<String> {
+        self.uid.clone()
+    }
+}
 impl Mergeable<Self> for AppAccount {
     fn merge(&mut self, other: Self) {
         self.auth.merge(other.auth);
+        self.can_reset_passwords.merge(other.can_reset_passwords);
         self.description.merge(other.description);
         self.extra_properties.merge(other.extra_properties);
         self.uid.merge(other.uid);
@@ -0,0 +1,68 @@
+use crate::ldap::{attributes::LDAPAttributes, config::traits::{AsLDAPAttributes, AugmentConfig, NaturalKey}, dn::LDAPDN, entry::LDAPEntry, traits::Mergeable};
+
+use super::ExtraProperties;
+
+/// Entries holding the other entities, every intermediate DN below the base DN gets one
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Container {
+    /// Description
+    pub description: Option<String>,
+
+    /// Distinguished name
+    pub dn: Option<LDAPDN>,
+
+    /// Extra properties
+    #[serde(flatten)] pub extra_properties: ExtraProperties,
+}
+impl AsLDAPAttributes for Container {
+    fn as_ldap_attributes(&self) -> LDAPAttributes {
+        let mut attributes = LDAPAttributes::default();
+
+        // structural object class and naming attribute, by the RDN
+        if let Some((k, v)) = self.dn.as_ref().and_then(|dn| dn.front()) {
+            match k.to_lowercase().as_str() {
+                "dc" => {
+                    attributes.add_value(super::OBJECT_CLASS, "dcObject");
+                    attributes.add_value(super::OBJECT_CLASS, "organization");
+                    attributes.add_value("o", v);
+                },
+                "ou" => attributes.add_value(super::OBJECT_CLASS, "organizationalUnit"),
+                "o" => attributes.add_value(super::OBJECT_CLASS, "organization"),
+                "c" => attributes.add_value(super::OBJECT_CLASS, "country"),
+                "l" => attributes.add_value(super::OBJECT_CLASS, "locality"),
+                _ => attributes.add_value(super::OBJECT_CLASS, "extensibleObject"),
+            }
+            attributes.add_value(k, v);
+        }
+
+        if let Some(description) = self.description.as_ref() {
+            attributes.add_value("description", description);
+        }
+        attributes.merge(self.extra_properties.as_ldap_attributes());
+        attributes
+    }
+}
+impl AugmentConfig for Container {
+    fn as_ldap_dn(&self, _config: &crate::ldap::config::Config) -> Option<LDAPDN> {
+        self.dn.clone()
+    }
+    fn as_ldap_entry(&self, _config: &crate::ldap::config::Config, _entries: &[LDAPEntry]) -> Option<LDAPEntry> {
+        if self.dn.as_ref().is_none_or(|dn| dn.is_empty()) {
+            tracing::warn!("Entry skipped: missing DN");
+            return None;
+        }
+        Some(LDAPEntry::new(self.dn.clone()?, self.as_ldap_attributes()))
+    }
+}
+impl NaturalKey for Container {
+    fn natural_key(&self) -> Option<String> {
+        self.dn.as_ref().map(|dn| dn.to_string())
+    }
+}
+impl Mergeable<Self> for Container {
+    fn merge(&mut self, other: Self) {
+        self.description.merge(other.description);
+        self.dn.merge(other.dn);
+        self.extra_properties.merge(other.extra_properties);
+    }
+}
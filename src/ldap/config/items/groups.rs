@@ -1,8 +1,49 @@
-use crate::ldap::{attributes::LDAPAttributes, config::traits::{AsLDAPAttributes, AugmentConfig}, datatypes::CIString, dn::LDAPDN, entry::LDAPEntry, traits::Mergeable};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use super::ExtraProperties;
+use crate::ldap::{attributes::LDAPAttributes, config::{layout::EntityKind, Config, traits::{AsLDAPAttributes, AugmentConfig, NaturalKey}}, dn::LDAPDN, entry::LDAPEntry, traits::Mergeable};
 
-/// Inspired by RFC4519 - groupOfUniqueNames
+use super::{ExtraProperties, User};
+
+/// Object classes a group may be emitted as, along with their membership attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupObjectClass {
+    /// RFC4519 - groupOfNames, users and nested groups in member
+    GroupOfNames,
+    /// RFC4519 - groupOfUniqueNames, users and nested groups in uniqueMember
+    GroupOfUniqueNames,
+    /// RFC2307 - posixGroup, user ids in memberUid, requires a gid number
+    PosixGroup,
+    /// RFC2307bis - groupOfMembers, users and nested groups in member, possibly empty
+    GroupOfMembers,
+}
+impl GroupObjectClass {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::GroupOfNames => "groupOfNames",
+            Self::GroupOfUniqueNames => "groupOfUniqueNames",
+            Self::PosixGroup => "posixGroup",
+            Self::GroupOfMembers => "groupOfMembers",
+        }
+    }
+
+    pub fn member_attribute(&self) -> &'static str {
+        match self {
+            Self::GroupOfNames | Self::GroupOfMembers => "member",
+            Self::GroupOfUniqueNames => "uniqueMember",
+            Self::PosixGroup => "memberUid",
+        }
+    }
+
+    /// Whether the membership attribute is mandatory
+    pub fn requires_members(&self) -> bool {
+        matches!(self, Self::GroupOfNames | Self::GroupOfUniqueNames)
+    }
+
+}
+
+/// Inspired by RFC4519 - groupOfUniqueNames, and RFC2307 - posixGroup when it has a gid number
 #[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct Group {
     /// Description
@@ -11,13 +52,151 @@ pub struct Group {
     /// Extra properties
     #[serde(flatten)] pub extra_properties: ExtraProperties,
 
+    /// Posix gid number
+    pub gid_number: Option<String>,
+
     /// Name
     pub name: Option<String>,
+
+    /// Names of the groups which are members of this group
+    #[serde(default)] pub member_groups: HashSet<String>,
+
+    /// Object classes to emit, the configured default when empty
+    #[serde(default)] pub object_classes: Vec<GroupObjectClass>,
+}
+impl Group {
+    /// The primary group of a user, named after it
+    pub fn primary_of(user: &User) -> Option<Self> {
+        let uid = user.uid.as_ref()?;
+        Some(Self {
+            description: Some(format!("Primary group of {uid}")),
+            gid_number: Some(user.gid_number.clone()?),
+            name: Some(uid.clone()),
+            ..Default::default()
+        })
+    }
+
+    /// The object classes to emit, posixGroup is added along with a gid number and dropped without one
+    pub fn effective_object_classes(&self, config: &Config) -> Vec<GroupObjectClass> {
+        let mut object_classes = [&self.object_classes, &config.group_object_classes].into_iter()
+            .find(|object_classes| !object_classes.is_empty())
+            .cloned()
+            .unwrap_or_else(|| vec![GroupObjectClass::GroupOfUniqueNames]);
+        if self.gid_number.is_none() {
+            object_classes.retain(|object_class| *object_class != GroupObjectClass::PosixGroup);
+        } else if !object_classes.contains(&GroupObjectClass::PosixGroup) {
+            object_classes.push(GroupObjectClass::PosixGroup);
+        }
+        object_classes
+    }
+
+    /// Drop the member groups closing a membership cycle, returning them as (group, member group) pairs
+    pub fn break_cycles(groups: &mut [Group]) -> Vec<(String, String)> {
+        let mut accepted = HashMap::<String, Vec<String>>::new();
+        let reaches = |accepted: &HashMap<String, Vec<String>>, from: &String, to: &String| {
+            let mut visited = HashSet::new();
+            let mut pending = vec![from];
+            while let Some(name) = pending.pop() {
+                if name == to {
+                    return true;
+                }
+                if visited.insert(name) {
+                    pending.extend(accepted.get(name).into_iter().flatten());
+                }
+            }
+            false
+        };
+
+        let mut removed = Vec::new();
+        for group in groups.iter_mut() {
+            let Some(group_name) = group.name.clone() else {
+                continue;
+            };
+            let mut member_group_names = group.member_groups.iter().cloned().collect::<Vec<_>>();
+            member_group_names.sort();
+            for member_group_name in member_group_names {
+                if reaches(&accepted, &member_group_name, &group_name) {
+                    tracing::warn!(group_name, member_group_name, "Group membership cycle, member group dropped from group");
+                    group.member_groups.remove(&member_group_name);
+                    removed.push((group_name.clone(), member_group_name));
+                } else {
+                    accepted.entry(group_name.clone()).or_default().push(member_group_name);
+                }
+            }
+        }
+        removed
+    }
+
+    /// The groups an entity belongs to given its direct ones, along with the groups containing them when memberOf is transitive
+    pub fn member_of<'a>(config: &'a Config, group_names: impl IntoIterator<Item = &'a String>) -> Vec<&'a Group> {
+        let mut groups = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = group_names.into_iter().collect::<VecDeque<_>>();
+        while let Some(group_name) = pending.pop_front() {
+            if !visited.insert(group_name) {
+                continue;
+            }
+            let Some(group) = config.data.groups.iter().find(|group| group.name.as_ref() == Some(group_name)) else {
+                tracing::warn!(group_name, "Group not found");
+                continue;
+            };
+            if group.is_emitted(config) {
+                groups.push(group);
+            } else {
+                tracing::debug!(group_name, "Group skipped in memberOf: no entry");
+            }
+            if config.transitive_member_of {
+                pending.extend(config.data.groups.iter()
+                    .filter(|parent| parent.member_groups.contains(group_name))
+                    .filter_map(|parent| parent.name.as_ref()));
+            }
+        }
+        groups
+    }
+
+    /// Whether this group gets an entry, i.e. it has a DN and an object class which does not require members or some members
+    pub fn is_emitted(&self, config: &Config) -> bool {
+        self.is_emitted_within(config, &mut HashSet::new())
+    }
+
+    fn is_emitted_within<'a>(&'a self, config: &'a Config, visited: &mut HashSet<&'a String>) -> bool {
+        let Some(group_name) = self.name.as_ref() else {
+            return false;
+        };
+        if !visited.insert(group_name) || self.as_ldap_dn(config).is_none() {
+            return false;
+        }
+        let object_classes = self.effective_object_classes(config);
+        if object_classes.iter().any(|object_class| !object_class.requires_members()) {
+            return true;
+        }
+        !object_classes.is_empty() && (
+            config.data.users.iter().any(|user| user.group_names.contains(group_name) && user.as_ldap_dn(config).is_some())
+            || self.member_groups.iter()
+                .filter_map(|member_group_name| config.data.groups.iter().find(|group| group.name.as_ref() == Some(member_group_name)))
+                .any(|member_group| member_group.is_emitted_within(config, visited))
+        )
+    }
+
+    /// The users belonging to this group, directly or through its member groups
+    fn transitive_member_users<'a>(&self, config: &'a Config) -> Vec<&'a User> {
+        let mut visited = HashSet::new();
+        let mut pending = self.name.iter().collect::<Vec<_>>();
+        while let Some(group_name) = pending.pop() {
+            if visited.insert(group_name) {
+                pending.extend(config.data.groups.iter()
+                    .filter(|group| group.name.as_ref() == Some(group_name))
+                    .flat_map(|group| group.member_groups.iter()));
+            }
+        }
+        config.data.users.iter()
+            .filter(|user| user.group_names.iter().any(|group_name| visited.contains(group_name)))
+            .collect()
+    }
 }
 impl AsLDAPAttributes for Group {
     fn as_ldap_attributes(&self) -> LDAPAttributes {
         let mut attributes = LDAPAttributes::default();
-        attributes.add_value(super::OBJECT_CLASS, "groupOfUniqueNames");
         if let Some(description) = self.description.as_ref() {
             attributes.add_value("description", description);
         }
@@ -25,48 +204,99 @@ impl AsLDAPAttributes for Group {
         if let Some(name) = self.name.as_ref() {
             attributes.add_value("cn", name);
         }
+        if let Some(gid_number) = self.gid_number.as_ref() {
+            attributes.add_value("gidNumber", gid_number);
+        }
         attributes
     }
 }
 impl AugmentConfig for Group {
-    fn as_ldap_dn(&self, base_dn: &LDAPDN) -> Option<crate::ldap::dn::LDAPDN> {
-        self.name.as_ref()
-            .map(|name|
-                base_dn.clone()
-                    .with_prefix("ou", "groups")
-                    .with_prefix("cn", name)
-                )
+    fn as_ldap_dn(&self, config: &crate::ldap::config::Config) -> Option<LDAPDN> {
+        config.layout.dn(EntityKind::Group, &config.base_dn, &self.as_ldap_attributes())
     }
     fn as_ldap_entry(&self, config: &crate::ldap::config::Config, _entries: &[crate::ldap::entry::LDAPEntry]) -> Option<crate::ldap::entry::LDAPEntry> {
         if self.name.is_none() {
             tracing::warn!("Entry skipped: missing name");
             return None;
         }
-        let mut entry = LDAPEntry::new(self.as_ldap_dn(&config.base_dn)?, self.as_ldap_attributes());
+        let mut entry = LDAPEntry::new(self.as_ldap_dn(config)?, self.as_ldap_attributes());
 
-        // uniqueMember
+        // members, users and nested groups
         let group_name = self.name.as_ref().unwrap();
-        for user in config.data.users.iter() {
-            if user.group_names.contains(group_name) {
-                if let Some(dn) = user.as_ldap_dn(&config.base_dn) {
-                    entry.attributes.add_value("uniqueMember", dn.to_string());
+        let mut member_dns = Vec::new();
+        for user in config.data.users.iter().filter(|user| user.group_names.contains(group_name)) {
+            if let Some(dn) = user.as_ldap_dn(config) {
+                member_dns.push(dn.to_string());
+            } else {
+                tracing::warn!(?user, "Empty DN");
+            }
+        }
+        for member_group_name in self.member_groups.iter() {
+            if let Some(member_group) = config.data.groups.iter().find(|group| group.name.as_ref() == Some(member_group_name)) {
+                if !member_group.is_emitted(config) {
+                    tracing::debug!(member_group_name, "Member group skipped: no entry");
+                } else if let Some(dn) = member_group.as_ldap_dn(config) {
+                    member_dns.push(dn.to_string());
                 } else {
-                    tracing::warn!(?user, "Empty DN");
+                    tracing::warn!(member_group_name, "Empty DN");
                 }
+            } else {
+                tracing::warn!(member_group_name, "Group not found");
             }
         }
 
-        if entry.attributes.has_attribute(&CIString::new("uniqueMember")) {
+        // member uids, flattened as posix clients don't follow nested groups
+        let member_uids = self.transitive_member_users(config).into_iter()
+            .filter_map(|user| user.uid.clone())
+            .collect::<Vec<_>>();
+
+        // object classes, skipping those requiring members when there are none
+        let mut has_object_class = false;
+        for object_class in self.effective_object_classes(config) {
+            let members = match object_class {
+                GroupObjectClass::PosixGroup => &member_uids,
+                _ => &member_dns,
+            };
+            if members.is_empty() && object_class.requires_members() {
+                tracing::debug!(group_name, ?object_class, "Object class skipped: no members");
+                continue;
+            }
+            entry.attributes.add_value_if_absent(super::OBJECT_CLASS, object_class.name());
+            for member in members.iter() {
+                entry.attributes.add_value_if_absent(object_class.member_attribute(), member);
+            }
+            has_object_class = true;
+        }
+
+        // memberOf
+        let parent_names = config.data.groups.iter()
+            .filter(|group| group.member_groups.contains(group_name))
+            .filter_map(|group| group.name.as_ref());
+        for group in Group::member_of(config, parent_names) {
+            if let Some(dn) = group.as_ldap_dn(config) {
+                entry.attributes.add_value("memberOf", dn.to_string());
+            }
+        }
+
+        if has_object_class {
             Some(entry)
         } else {
             None
         }
     }
 }
+impl NaturalKey for Group {
+    fn natural_key(&self) -> Option<String> {
+        self.name.clone()
+    }
+}
 impl Mergeable<Self> for Group {
     fn merge(&mut self, other: Self) {
         self.description.merge(other.description);
         self.extra_properties.merge(other.extra_properties);
+        self.gid_number.merge(other.gid_number);
         self.name.merge(other.name);
+        self.member_groups.merge(other.member_groups);
+        self.object_classes.merge(other.object_classes);
     }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::ldap::{attributes::LDAPAttributes, config::traits::{AsLDAPAttributes, AugmentConfig}, entry::LDAPEntry, traits::Mergeable};
+use crate::ldap::{attributes::LDAPAttributes, config::{layout::EntityKind, traits::{AsLDAPAttributes, AugmentConfig, NaturalKey}}, entry::LDAPEntry, traits::Mergeable};
 
 use super::ExtraProperties;
 
@@ -31,14 +31,8 @@ impl AsLDAPAttributes for MailAlias {
 }
 impl AugmentConfig for MailAlias {
 
-    fn as_ldap_dn(&self, base_dn: &crate::ldap::dn::LDAPDN) -> Option<crate::ldap::dn::LDAPDN> {
-        self.mail.as_ref()
-            .map(|name|
-                base_dn.clone()
-                    .with_prefix("ou", "mail")
-                    .with_prefix("ou", "aliases")
-                    .with_prefix("cn", name)
-                )
+    fn as_ldap_dn(&self, config: &crate::ldap::config::Config) -> Option<crate::ldap::dn::LDAPDN> {
+        config.layout.dn(EntityKind::MailAlias, &config.base_dn, &self.as_ldap_attributes())
     }
 
     #[tracing::instrument(skip(config, _entries))]
@@ -48,7 +42,7 @@ impl AugmentConfig for MailAlias {
             return None;
         }
         let mail = self.mail.as_ref().unwrap();
-        let mut entry = LDAPEntry::new(self.as_ldap_dn(&config.base_dn)?, self.as_ldap_attributes());
+        let mut entry = LDAPEntry::new(self.as_ldap_dn(config)?, self.as_ldap_attributes());
 
         // aliases for users
         for user in config.data.users.iter() {
@@ -64,6 +58,11 @@ impl AugmentConfig for MailAlias {
         Some(entry)
     }
 }
+impl NaturalKey for MailAlias {
+    fn natural_key(&self) -> Option<String> {
+        self.mail.clone()
+    }
+}
 impl Mergeable<Self> for MailAlias {
     fn merge(&mut self, other: Self) {
         self.aliases.merge(other.aliases);
@@ -1,4 +1,5 @@
 mod app_accounts; pub use app_accounts::*;
+mod containers; pub use containers::*;
 mod groups; pub use groups::*;
 mod login_properties; pub use login_properties::*;
 mod extra_properties; pub use extra_properties::*;
@@ -6,4 +7,4 @@ mod mail_aliases; pub use mail_aliases::*;
 mod root_dse; pub use root_dse::*;
 mod users; pub use users::*;
 
-pub const OBJECT_CLASS: &'static str = "objectClass";
+pub const OBJECT_CLASS: &str = "objectClass";
@@ -1,9 +1,9 @@
-use crate::ldap::{attributes::LDAPAttributes, config::{traits::AugmentConfig, Config}, dn::LDAPDN, entry::LDAPEntry};
+use crate::ldap::{attributes::LDAPAttributes, config::{traits::AugmentConfig, Config}, dn::LDAPDN, entry::LDAPEntry, password::PasswordScheme};
 
 #[derive(Default)]
 pub struct RootDSE;
 impl AugmentConfig for RootDSE {
-    fn as_ldap_dn(&self, _base_dn: &LDAPDN) -> Option<LDAPDN> {
+    fn as_ldap_dn(&self, _config: &Config) -> Option<LDAPDN> {
         Some(LDAPDN::empty())
     }
     fn as_ldap_entry(&self, config: &Config, _entries: &[LDAPEntry]) -> Option<LDAPEntry> {
@@ -12,7 +12,11 @@ impl AugmentConfig for RootDSE {
         entry.attributes.add_value(super::OBJECT_CLASS, "extensibleObject");
         entry.attributes.add_value("dsaName", "femtoLDAP");
         entry.attributes.add_value("namingContexts", config.base_dn.to_string());
-        entry.attributes.add_value("supportedAuthPasswordSchemes", "CLEAR");
+        PasswordScheme::all()
+            .filter(|scheme| !(config.reject_cleartext_passwords && *scheme == PasswordScheme::Cleartext))
+            .for_each(|scheme| entry.attributes.add_value("supportedAuthPasswordSchemes", scheme.name()));
+        entry.attributes.add_value("supportedControl", "1.2.840.113556.1.4.319"); // RFC 2696 - Simple Paged Results
+        entry.attributes.add_value("supportedExtension", "1.3.6.1.4.1.4203.1.11.3"); // RFC 4532 - WhoAmI
         entry.attributes.add_value("supportedLDAPVersion", "3");
         entry.attributes.add_value("vendorName", "femtoldap");
         entry.attributes.add_value("vendorVersion", "whatever");
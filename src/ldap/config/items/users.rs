@@ -1,9 +1,9 @@
 use serde_with::{formats::PreferOne, OneOrMany};
 use std::collections::HashSet;
 
-use crate::ldap::{attributes::LDAPAttributes, config::traits::{AsLDAPAttributes, AugmentConfig}, entry::LDAPEntry, traits::Mergeable};
+use crate::ldap::{attributes::LDAPAttributes, config::{layout::EntityKind, traits::{AsLDAPAttributes, AugmentConfig, NaturalKey}}, datatypes::CIString, entry::LDAPEntry, traits::Mergeable};
 
-use super::{ExtraProperties, LoginProperties};
+use super::{ExtraProperties, Group, LoginProperties};
 
 #[serde_with::serde_as]
 #[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
@@ -127,14 +127,23 @@ impl AsLDAPAttributes for User {
         attributes
     }
 }
+impl User {
+    /// The attributes along with a common name when the layout names users by it
+    pub fn layout_attributes(&self, config: &crate::ldap::config::Config) -> LDAPAttributes {
+        let mut attributes = self.as_ldap_attributes();
+        let cn = CIString::new("cn");
+        if CIString::new(config.layout.rdn_attribute(EntityKind::User)) == cn && !attributes.has_attribute(&cn) {
+            let display_name = attributes.get_attribute(&CIString::new("displayName")).and_then(|attribute| attribute.values.first()).cloned();
+            if let Some(value) = display_name.or_else(|| self.uid.as_ref().map(|uid| uid.as_bytes().to_vec())) {
+                attributes.add_value("cn", value);
+            }
+        }
+        attributes
+    }
+}
 impl AugmentConfig for User {
-    fn as_ldap_dn(&self, base_dn: &crate::ldap::dn::LDAPDN) -> Option<crate::ldap::dn::LDAPDN> {
-        self.uid.as_ref()
-            .map(|name|
-                base_dn.clone()
-                    .with_prefix("ou", "users")
-                    .with_prefix("uid", name)
-                )
+    fn as_ldap_dn(&self, config: &crate::ldap::config::Config) -> Option<crate::ldap::dn::LDAPDN> {
+        config.layout.dn(EntityKind::User, &config.base_dn, &self.layout_attributes(config))
     }
     fn as_ldap_entry(&self, config: &crate::ldap::config::Config, _entries: &[crate::ldap::entry::LDAPEntry]) -> Option<crate::ldap::entry::LDAPEntry> {
         //
@@ -147,25 +156,27 @@ impl AugmentConfig for User {
         //
         // Assemble entry
         //
-        let mut entry = LDAPEntry::new(self.as_ldap_dn(&config.base_dn)?, self.as_ldap_attributes());
+        let mut entry = LDAPEntry::new(self.as_ldap_dn(config)?, self.layout_attributes(config));
+        entry.kind = Some(EntityKind::User);
         entry.acls.can_access_self = true;
 
         // memberOf
-        for group_name in self.group_names.iter() {
-            if let Some(group) = config.data.groups.iter().find(|group| group.name.as_ref().map(|name| name == group_name).unwrap_or(false)) {
-                if let Some(dn) = group.as_ldap_dn(&config.base_dn) {
-                    entry.attributes.add_value("memberOf", dn.to_string());
-                } else {
-                    tracing::warn!(group_name, "Empty DN");
-                }
+        for group in Group::member_of(config, self.group_names.iter()) {
+            if let Some(dn) = group.as_ldap_dn(config) {
+                entry.attributes.add_value("memberOf", dn.to_string());
             } else {
-                tracing::warn!(group_name, "Group not found");
+                tracing::warn!(?group, "Empty DN");
             }
         }
 
         Some(entry)
     }
 }
+impl NaturalKey for User {
+    fn natural_key(&self) -> Option<String> {
+        self.uid.clone()
+    }
+}
 impl Mergeable<Self> for User {
     fn merge(&mut self, other: Self) {
         self.auth.merge(other.auth);
@@ -0,0 +1,168 @@
+use crate::ldap::{attributes::LDAPAttributes, datatypes::CIString, dn::LDAPDN, traits::Mergeable};
+
+/// Kinds of configured entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    App,
+    Group,
+    MailAlias,
+    User,
+}
+
+/// Placement and naming of the entities of a kind, unset fields keep the defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct EntityLayout {
+    /// Container of the entities, relative to the base DN
+    pub container: Option<LDAPDN>,
+
+    /// Attribute naming the entities, its first value is used in their RDN
+    pub rdn_attribute: Option<String>,
+}
+impl Mergeable<Self> for EntityLayout {
+    fn merge(&mut self, other: Self) {
+        self.container.merge(other.container);
+        self.rdn_attribute.merge(other.rdn_attribute);
+    }
+}
+
+/// Placement and naming of the entities in the tree
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct DNLayout {
+    #[serde(default)] pub apps: EntityLayout,
+    #[serde(default)] pub groups: EntityLayout,
+    #[serde(default)] pub mail_aliases: EntityLayout,
+    #[serde(default)] pub users: EntityLayout,
+}
+impl DNLayout {
+
+    /// The layout of a kind, along with its default container and RDN attribute
+    fn entity_layout(&self, kind: EntityKind) -> (&EntityLayout, &'static [(&'static str, &'static str)], &'static str) {
+        match kind {
+            EntityKind::App => (&self.apps, &[("ou", "apps")], "uid"),
+            EntityKind::Group => (&self.groups, &[("ou", "groups")], "cn"),
+            EntityKind::MailAlias => (&self.mail_aliases, &[("ou", "aliases"), ("ou", "mail")], "cn"),
+            EntityKind::User => (&self.users, &[("ou", "users")], "uid"),
+        }
+    }
+
+    /// DN of the container of a kind
+    pub fn container_dn(&self, kind: EntityKind, base_dn: &LDAPDN) -> LDAPDN {
+        let (entity_layout, default_container, _) = self.entity_layout(kind);
+        let mut dn = base_dn.clone();
+        match &entity_layout.container {
+            Some(container) => container.iter().rev().for_each(|(k, v)| dn.add_prefix(k, v)),
+            None => default_container.iter().rev().for_each(|(k, v)| dn.add_prefix(*k, *v)),
+        }
+        dn
+    }
+
+    pub fn rdn_attribute(&self, kind: EntityKind) -> &str {
+        let (entity_layout, _, default_rdn_attribute) = self.entity_layout(kind);
+        entity_layout.rdn_attribute.as_deref().unwrap_or(default_rdn_attribute)
+    }
+
+    /// Value of the RDN attribute of an entity
+    pub fn rdn_value(&self, kind: EntityKind, attributes: &LDAPAttributes) -> Option<String> {
+        let rdn_value = attributes.get_attribute(&CIString::new(self.rdn_attribute(kind)))?.values.first()?;
+        Some(String::from_utf8_lossy(rdn_value).into_owned())
+    }
+
+    /// DN of an entity, `None` when it lacks the RDN attribute or its value can't be used unescaped
+    pub fn dn(&self, kind: EntityKind, base_dn: &LDAPDN, attributes: &LDAPAttributes) -> Option<LDAPDN> {
+        let rdn_attribute = self.rdn_attribute(kind);
+        let rdn_value = self.rdn_value(kind, attributes)?;
+        if !is_plain_rdn_value(&rdn_value) {
+            tracing::warn!(rdn_attribute, rdn_value, "Entry skipped: RDN value requires escaping");
+            return None;
+        }
+        Some(self.container_dn(kind, base_dn).with_prefix(rdn_attribute, rdn_value))
+    }
+
+}
+/// Whether a value can be used in a DN without escaping (RFC 4514 section 2.4), escaped DNs are not supported
+pub fn is_plain_rdn_value(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with(['#', ' '])
+        && !value.ends_with(' ')
+        && !value.contains(['"', '+', ',', ';', '<', '>', '\\', '=', '\0'])
+}
+
+impl Mergeable<Self> for DNLayout {
+    fn merge(&mut self, other: Self) {
+        self.apps.merge(other.apps);
+        self.groups.merge(other.groups);
+        self.mail_aliases.merge(other.mail_aliases);
+        self.users.merge(other.users);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ldap::{config::fixtures, datatypes::CIString};
+
+    #[test]
+    fn test_dn_layout() {
+        let config = fixtures::config(r#"
+            [layout.users]
+            container = "ou=People"
+            rdn_attribute = "cn"
+
+            [layout.apps]
+            container = "ou=Services"
+
+            [[apps]]
+            uid = "app"
+
+            [[groups]]
+            name = "group"
+
+            [[users]]
+            uid = "user"
+            name = "Full"
+            surname = "Name"
+            group_names = [ "group" ]
+        "#);
+        let entries = config.assemble_entries();
+
+        assert_eq!(fixtures::attributes(&entries, "cn=Full Name,ou=People,dc=example,dc=com", "memberOf"), vec!["cn=group,ou=groups,dc=example,dc=com"]);
+        assert_eq!(fixtures::attributes(&entries, "cn=group,ou=groups,dc=example,dc=com", "uniqueMember"), vec!["cn=Full Name,ou=People,dc=example,dc=com"]);
+        assert_eq!(fixtures::attributes(&entries, "ou=People,dc=example,dc=com", "objectClass"), vec!["organizationalUnit"]);
+        assert_eq!(fixtures::attributes(&entries, "uid=app,ou=Services,dc=example,dc=com", "uid"), vec!["app"]);
+
+        // apps can't see each other under the configured container
+        let app = entries.iter().find(|entry| entry.dn.to_string() == "uid=app,ou=Services,dc=example,dc=com").unwrap();
+        assert_eq!(app.acls.cant_access_suffixes.iter().map(|dn| dn.to_string()).collect::<Vec<_>>(), vec!["ou=Services,dc=example,dc=com"]);
+    }
+
+    #[test]
+    fn test_dn_layout_special_characters() {
+        let config = fixtures::config(r#"
+            [layout.users]
+            rdn_attribute = "cn"
+
+            [[groups]]
+            name = "group"
+
+            [[users]]
+            uid = "john"
+            display_name = "Doe, John"
+            group_names = [ "group" ]
+
+            [[users]]
+            uid = "jane"
+            display_name = "Jane Doe"
+            group_names = [ "group" ]
+        "#);
+        let entries = config.assemble_entries();
+
+        // the entity is left out, along with references to it
+        assert!(!entries.iter().any(|entry| entry.attributes.get_attribute(&CIString::new("uid")).is_some_and(|attribute| attribute.values == vec![b"john".to_vec()])));
+        assert_eq!(fixtures::attributes(&entries, "cn=group,ou=groups,dc=example,dc=com", "uniqueMember"), vec!["cn=Jane Doe,ou=users,dc=example,dc=com"]);
+
+        assert!(super::is_plain_rdn_value("Jane Doe"));
+        for value in [ "Doe, John", "a+b", "a=b", " a", "a ", "#a", "" ] {
+            assert!(!super::is_plain_rdn_value(value), "{value:?}");
+        }
+    }
+
+}
@@ -1,9 +1,17 @@
-use items::{AppAccount, Group, MailAlias, RootDSE, User};
-use traits::AugmentConfig;
+use std::collections::HashSet;
 
-use super::{datatypes::CIString, dn::LDAPDN, entry::LDAPEntry, traits::Mergeable};
+use items::{AppAccount, Container, Group, GroupObjectClass, MailAlias, RootDSE, User};
+use layout::DNLayout;
+use traits::{AugmentConfig, NaturalKey};
 
+use super::{datatypes::CIString, dn::LDAPDN, entry::LDAPEntry, password::is_cleartext, schema, traits::Mergeable};
+
+pub mod check;
+#[cfg(test)]
+pub mod fixtures;
 pub mod items;
+pub mod layout;
+pub mod overlay;
 pub mod traits;
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
@@ -11,18 +19,57 @@ pub struct Config {
     /// The base DN for all entities
     pub base_dn: LDAPDN,
 
+    /// Refuse to load passwords which are not hashed
+    #[serde(default)]
+    pub reject_cleartext_passwords: bool,
+
+    /// Synthesize a posix group named after each user with a gid number, unless a group already has that gid number
+    #[serde(default)]
+    pub primary_groups: bool,
+
+    /// List in memberOf the groups containing the groups of an entity as well
+    #[serde(default)]
+    pub transitive_member_of: bool,
+
+    /// Object classes emitted for the groups which don't choose theirs, groupOfUniqueNames when empty
+    #[serde(default)]
+    pub group_object_classes: Vec<GroupObjectClass>,
+
+    /// Placement and naming of the entities
+    #[serde(default)]
+    pub layout: DNLayout,
+
     /// The actual contents of the directory
     #[serde(flatten)]
     pub data: DirectoryContents,
 }
 impl Config {
+    /// Enforce the password policy of the configuration
+    pub fn check_passwords(&self) -> anyhow::Result<()> {
+        if !self.reject_cleartext_passwords {
+            return Ok(());
+        }
+        let apps = self.data.apps.iter().map(|app| (app.as_ldap_dn(self), &app.auth));
+        let users = self.data.users.iter().map(|user| (user.as_ldap_dn(self), &user.auth));
+        let offending_dns = apps.chain(users)
+            .filter(|(_, auth)| auth.password.as_deref().is_some_and(is_cleartext))
+            .filter_map(|(dn, _)| dn.map(|dn| dn.to_string()))
+            .collect::<Vec<_>>();
+        if offending_dns.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Cleartext passwords are not allowed: {}", offending_dns.join("; ")))
+        }
+    }
+
     pub fn assemble_entries(&self) -> Vec<LDAPEntry> {
         // prepare config and entires
-        let config = self.clone();
+        let mut config = self.clone();
+        Group::break_cycles(&mut config.data.groups);
         let mut entries = Vec::new();
 
         // root DSE
-        entries.push(RootDSE::default().as_ldap_entry(&config, &entries).unwrap());
+        entries.push(RootDSE.as_ldap_entry(&config, &entries).unwrap());
 
         // applications
         for application in config.data.apps.iter() {
@@ -37,6 +84,17 @@ impl Config {
                 entries.push(entry);
             }
         }
+        if config.primary_groups {
+            for group in config.data.users.iter().filter_map(Group::primary_of) {
+                if config.data.groups.iter().any(|other| other.gid_number == group.gid_number || other.name == group.name) {
+                    tracing::debug!(?group, "Primary group already configured");
+                    continue;
+                }
+                if let Some(entry) = group.as_ldap_entry(&config, &entries) {
+                    entries.push(entry);
+                }
+            }
+        }
 
         // mail aliases
         for mail_alias in config.data.mail_aliases.iter() {
@@ -52,6 +110,34 @@ impl Config {
             }
         }
 
+        // containers, configured and for every intermediate DN down from the base DN
+        let mut container_dns = config.data.containers.iter()
+            .filter_map(|container| container.as_ldap_dn(&config))
+            .collect::<Vec<_>>();
+        let mut existing_dns = entries.iter().map(|entry| &entry.dn)
+            .chain(container_dns.iter())
+            .map(schema::normalize_dn)
+            .collect::<HashSet<_>>();
+        for entry in entries.iter() {
+            let mut ancestor = entry.dn.parent();
+            while let Some(dn) = ancestor.filter(|dn| !dn.is_empty() && dn.matches_suffix(&config.base_dn)) {
+                ancestor = dn.parent();
+                if existing_dns.insert(schema::normalize_dn(&dn)) {
+                    container_dns.push(dn);
+                }
+            }
+        }
+        container_dns.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        for dn in container_dns {
+            let container = config.data.containers.iter()
+                .find(|container| container.dn.as_ref() == Some(&dn))
+                .cloned()
+                .unwrap_or_else(|| Container { dn: Some(dn), ..Default::default() });
+            if let Some(entry) = container.as_ldap_entry(&config, &entries) {
+                entries.push(entry);
+            }
+        }
+
         // add entryDN operational attribute
         entries
             .iter_mut()
@@ -76,6 +162,11 @@ impl Config {
 impl Mergeable<Self> for Config {
     fn merge(&mut self, other: Self) {
         self.base_dn.merge(other.base_dn);
+        self.reject_cleartext_passwords.merge(other.reject_cleartext_passwords);
+        self.primary_groups.merge(other.primary_groups);
+        self.transitive_member_of.merge(other.transitive_member_of);
+        self.group_object_classes.merge(other.group_object_classes);
+        self.layout.merge(other.layout);
         self.data.merge(other.data);
     }
 }
@@ -85,6 +176,8 @@ pub struct DirectoryContents {
     #[serde(default)]
     pub apps: Vec<AppAccount>,
     #[serde(default)]
+    pub containers: Vec<Container>,
+    #[serde(default)]
     pub groups: Vec<Group>,
     #[serde(default)]
     pub mail_aliases: Vec<MailAlias>,
@@ -93,16 +186,16 @@ pub struct DirectoryContents {
 }
 impl DirectoryContents {
     #[tracing::instrument(skip_all)]
-    fn merge_to_entity_with_same_dn_or_add<T: AugmentConfig + Mergeable<T> + std::fmt::Debug>(
+    fn merge_to_entity_with_same_key_or_add<T: NaturalKey + Mergeable<T> + std::fmt::Debug>(
         search_entity: T,
         search_domain: &mut Vec<T>,
     ) {
-        let base_dn = LDAPDN::empty();
-        if let Some(search_dn) = search_entity.as_ldap_dn(&base_dn) {
+        // identity is the natural key, as the DNs depend on a layout which may come from a later file
+        if let Some(search_key) = search_entity.natural_key() {
             match search_domain.iter_mut().find(|entity| {
                 entity
-                    .as_ldap_dn(&base_dn)
-                    .map(|entity_dn| entity_dn == search_dn)
+                    .natural_key()
+                    .map(|entity_key| entity_key == search_key)
                     .unwrap_or(false)
             }) {
                 Some(entity) => {
@@ -115,15 +208,261 @@ impl DirectoryContents {
                 },
             }
         } else {
-            tracing::warn!(?search_entity, "Could not create entity DN");
+            tracing::warn!(?search_entity, "Could not identify entity");
         }
     }
 }
 impl Mergeable<Self> for DirectoryContents {
     fn merge(&mut self, mut other: Self) {
-        other.apps.drain(..).for_each(|app| Self::merge_to_entity_with_same_dn_or_add(app, &mut self.apps));
-        other.groups.drain(..).for_each(|group| Self::merge_to_entity_with_same_dn_or_add(group, &mut self.groups));
-        other.mail_aliases.drain(..).for_each(|mail_alias| Self::merge_to_entity_with_same_dn_or_add(mail_alias, &mut self.mail_aliases));
-        other.users.drain(..).for_each(|user| Self::merge_to_entity_with_same_dn_or_add(user, &mut self.users));
+        other.apps.drain(..).for_each(|app| Self::merge_to_entity_with_same_key_or_add(app, &mut self.apps));
+        other.containers.drain(..).for_each(|container| Self::merge_to_entity_with_same_key_or_add(container, &mut self.containers));
+        other.groups.drain(..).for_each(|group| Self::merge_to_entity_with_same_key_or_add(group, &mut self.groups));
+        other.mail_aliases.drain(..).for_each(|mail_alias| Self::merge_to_entity_with_same_key_or_add(mail_alias, &mut self.mail_aliases));
+        other.users.drain(..).for_each(|user| Self::merge_to_entity_with_same_key_or_add(user, &mut self.users));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ldap::traits::Mergeable;
+
+    use super::fixtures::{self, attributes};
+
+    #[test]
+    fn test_check_passwords() {
+        let mut config = fixtures::config(r#"
+            reject_cleartext_passwords = true
+
+            [[users]]
+            uid = "user1"
+            password = "{SSHA}gVK8WC9YyFT1gMsQHTGCgT3sSv5zYWx0"
+        "#);
+        assert!(config.check_passwords().is_ok());
+
+        config.data.users[0].auth.password = Some("secret".to_string());
+        assert!(config.check_passwords().is_err());
+    }
+
+    #[test]
+    fn test_merge_with_layout() {
+        let mut config = fixtures::config(r#"
+            [layout.users]
+            container = "ou=People"
+            rdn_attribute = "cn"
+
+            [[users]]
+            uid = "alice"
+            display_name = "Alice Smith"
+        "#);
+        config.merge(fixtures::config(r#"
+            [[users]]
+            uid = "alice"
+            display_name = "Alice S."
+            mail = "alice@example.com"
+        "#));
+
+        // the definitions of a user are merged by uid, whatever names its entry
+        assert_eq!(config.data.users.len(), 1);
+        let entries = config.assemble_entries();
+        assert_eq!(attributes(&entries, "cn=Alice Smith,ou=People,dc=example,dc=com", "mail"), vec!["alice@example.com"]);
+        assert!(config.check_items(&config).is_empty());
+    }
+
+    #[test]
+    fn test_container_entries() {
+        let config = fixtures::config(r#"
+            [[containers]]
+            dn = "ou=users,dc=example,dc=com"
+            description = "People"
+
+            [[containers]]
+            dn = "OU=Mail,dc=example,dc=com"
+
+            [[users]]
+            uid = "user1"
+
+            [[mail_aliases]]
+            mail = "alias@example.com"
+            aliases = [ "user1@example.com" ]
+        "#);
+        let entries = config.assemble_entries();
+
+        let mut dns = entries.iter().map(|entry| entry.dn.to_string()).collect::<Vec<_>>();
+        dns.sort();
+        assert_eq!(dns, vec![
+            "<root DSE>",
+            "OU=Mail,dc=example,dc=com",
+            "cn=alias@example.com,ou=aliases,ou=mail,dc=example,dc=com",
+            "dc=example,dc=com",
+            "ou=aliases,ou=mail,dc=example,dc=com",
+            "ou=users,dc=example,dc=com",
+            "uid=user1,ou=users,dc=example,dc=com",
+        ]);
+        assert_eq!(attributes(&entries, "dc=example,dc=com", "objectClass"), vec!["dcObject", "organization"]);
+        assert_eq!(attributes(&entries, "dc=example,dc=com", "o"), vec!["example"]);
+        assert_eq!(attributes(&entries, "ou=aliases,ou=mail,dc=example,dc=com", "objectClass"), vec!["organizationalUnit"]);
+        assert_eq!(attributes(&entries, "ou=aliases,ou=mail,dc=example,dc=com", "ou"), vec!["aliases"]);
+        assert_eq!(attributes(&entries, "ou=users,dc=example,dc=com", "description"), vec!["People"]);
+    }
+
+    #[test]
+    fn test_posix_groups() {
+        let config = fixtures::config(r#"
+            primary_groups = true
+
+            [[groups]]
+            name = "staff"
+            gid_number = "2000"
+
+            [[groups]]
+            name = "empty"
+            gid_number = "2001"
+
+            [[users]]
+            uid = "user1"
+            gid_number = "1000"
+            group_names = [ "staff" ]
+
+            [[users]]
+            uid = "user2"
+            gid_number = "2000"
+            group_names = [ "staff" ]
+        "#);
+        let entries = config.assemble_entries();
+
+        // configured groups, with and without members
+        assert_eq!(attributes(&entries, "cn=staff,ou=groups,dc=example,dc=com", "objectClass"), vec!["groupOfUniqueNames", "posixGroup"]);
+        assert_eq!(attributes(&entries, "cn=staff,ou=groups,dc=example,dc=com", "gidNumber"), vec!["2000"]);
+        assert_eq!(attributes(&entries, "cn=staff,ou=groups,dc=example,dc=com", "memberUid"), vec!["user1", "user2"]);
+        assert_eq!(attributes(&entries, "cn=empty,ou=groups,dc=example,dc=com", "objectClass"), vec!["posixGroup"]);
+        assert_eq!(attributes(&entries, "cn=empty,ou=groups,dc=example,dc=com", "memberUid"), Vec::<String>::new());
+
+        // primary groups, unless the gid number is taken
+        assert_eq!(attributes(&entries, "cn=user1,ou=groups,dc=example,dc=com", "objectClass"), vec!["posixGroup"]);
+        assert_eq!(attributes(&entries, "cn=user1,ou=groups,dc=example,dc=com", "gidNumber"), vec!["1000"]);
+        assert!(!entries.iter().any(|entry| entry.dn.to_string() == "cn=user2,ou=groups,dc=example,dc=com"));
+    }
+
+    #[test]
+    fn test_nested_groups() {
+        let mut config = fixtures::config(r#"
+            [[groups]]
+            name = "everyone"
+            member_groups = [ "staff" ]
+
+            [[groups]]
+            name = "staff"
+            gid_number = "2000"
+            member_groups = [ "admins" ]
+
+            [[groups]]
+            name = "admins"
+            member_groups = [ "everyone" ]
+
+            [[users]]
+            uid = "alice"
+            group_names = [ "admins" ]
+        "#);
+        let entries = config.assemble_entries();
+
+        // the cycle is broken on the last membership
+        assert_eq!(attributes(&entries, "cn=staff,ou=groups,dc=example,dc=com", "uniqueMember"), vec!["cn=admins,ou=groups,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "cn=everyone,ou=groups,dc=example,dc=com", "uniqueMember"), vec!["cn=staff,ou=groups,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "cn=admins,ou=groups,dc=example,dc=com", "uniqueMember"), vec!["uid=alice,ou=users,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "cn=staff,ou=groups,dc=example,dc=com", "memberUid"), vec!["alice"]);
+
+        // check reports the membership the entries leave out
+        assert_eq!(config.check_items(&config).iter().map(|problem| problem.to_string()).collect::<Vec<_>>(), vec![
+            "groups[2]: member group everyone closes a membership cycle, it is dropped from the group",
+        ]);
+
+        // direct memberOf
+        assert_eq!(attributes(&entries, "uid=alice,ou=users,dc=example,dc=com", "memberOf"), vec!["cn=admins,ou=groups,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "cn=admins,ou=groups,dc=example,dc=com", "memberOf"), vec!["cn=staff,ou=groups,dc=example,dc=com"]);
+
+        // transitive memberOf
+        config.transitive_member_of = true;
+        let entries = config.assemble_entries();
+        assert_eq!(attributes(&entries, "uid=alice,ou=users,dc=example,dc=com", "memberOf"), vec![
+            "cn=admins,ou=groups,dc=example,dc=com",
+            "cn=everyone,ou=groups,dc=example,dc=com",
+            "cn=staff,ou=groups,dc=example,dc=com",
+        ]);
+    }
+
+    #[test]
+    fn test_group_object_classes() {
+        let config = fixtures::config(r#"
+            group_object_classes = [ "groupOfNames" ]
+
+            [[groups]]
+            name = "default"
+
+            [[groups]]
+            name = "compatible"
+            gid_number = "2000"
+            object_classes = [ "groupOfNames", "groupOfUniqueNames", "groupOfMembers" ]
+
+            [[groups]]
+            name = "empty"
+            object_classes = [ "groupOfNames", "groupOfMembers" ]
+
+            [[groups]]
+            name = "unused"
+
+            [[users]]
+            uid = "user1"
+            group_names = [ "default", "compatible" ]
+        "#);
+        let entries = config.assemble_entries();
+
+        // global default
+        assert_eq!(attributes(&entries, "cn=default,ou=groups,dc=example,dc=com", "objectClass"), vec!["groupOfNames"]);
+        assert_eq!(attributes(&entries, "cn=default,ou=groups,dc=example,dc=com", "member"), vec!["uid=user1,ou=users,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "cn=default,ou=groups,dc=example,dc=com", "uniqueMember"), Vec::<String>::new());
+
+        // several at once, member is not repeated
+        assert_eq!(attributes(&entries, "cn=compatible,ou=groups,dc=example,dc=com", "objectClass"), vec!["groupOfMembers", "groupOfNames", "groupOfUniqueNames", "posixGroup"]);
+        assert_eq!(attributes(&entries, "cn=compatible,ou=groups,dc=example,dc=com", "member"), vec!["uid=user1,ou=users,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "cn=compatible,ou=groups,dc=example,dc=com", "uniqueMember"), vec!["uid=user1,ou=users,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "cn=compatible,ou=groups,dc=example,dc=com", "memberUid"), vec!["user1"]);
+
+        // groupOfMembers may be empty, groupOfNames may not
+        assert_eq!(attributes(&entries, "cn=empty,ou=groups,dc=example,dc=com", "objectClass"), vec!["groupOfMembers"]);
+        assert!(!entries.iter().any(|entry| entry.dn.to_string() == "cn=unused,ou=groups,dc=example,dc=com"));
+    }
+
+    #[test]
+    fn test_skipped_groups() {
+        let config = fixtures::config(r#"
+            [[groups]]
+            name = "parent"
+            member_groups = [ "nested", "unused" ]
+
+            [[groups]]
+            name = "nested"
+            member_groups = [ "unused" ]
+
+            [[groups]]
+            name = "unused"
+
+            [[groups]]
+            name = "misconfigured"
+            object_classes = [ "posixGroup" ]
+
+            [[users]]
+            uid = "user1"
+            group_names = [ "misconfigured", "parent" ]
+        "#);
+        let entries = config.assemble_entries();
+
+        // groups without an entry, including those whose only members have none
+        for name in ["nested", "unused", "misconfigured"] {
+            assert!(!entries.iter().any(|entry| entry.dn.to_string() == format!("cn={name},ou=groups,dc=example,dc=com")));
+        }
+
+        // and they are not referenced
+        assert_eq!(attributes(&entries, "cn=parent,ou=groups,dc=example,dc=com", "uniqueMember"), vec!["uid=user1,ou=users,dc=example,dc=com"]);
+        assert_eq!(attributes(&entries, "uid=user1,ou=users,dc=example,dc=com", "memberOf"), vec!["cn=parent,ou=groups,dc=example,dc=com"]);
     }
 }
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use crate::ldap::{dn::LDAPDN, traits::Mergeable};
+
+use super::{items::LoginProperties, traits::AugmentConfig, Config};
+
+/// Machine-managed credentials, changed by clients and applied over the configuration on every load
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct CredentialsOverlay {
+    pub credentials: BTreeMap<LDAPDN, LoginProperties>,
+}
+impl CredentialsOverlay {
+
+    /// Load the overlay file, which may not exist yet
+    pub async fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Replace the overlay file atomically
+    pub async fn store(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        let contents = toml::to_string(self)?;
+        // hidden and without a `.toml` extension, for the config directory loader and watcher to skip it
+        let file_name = path.file_name().ok_or_else(|| anyhow::anyhow!("Invalid overlay path {}", path.display()))?;
+        let tmp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp_path).await?;
+        tokio::io::AsyncWriteExt::write_all(&mut file, contents.as_bytes()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+
+    pub fn set_password(&mut self, dn: LDAPDN, password: impl Into<String>) {
+        self.credentials.entry(dn).or_default().password = Some(password.into());
+    }
+
+    /// Override the credentials of the configured entities, entities which are not configured are left out
+    pub fn apply(&self, config: &mut Config) {
+        let apply = |dn: Option<LDAPDN>, auth: &mut LoginProperties| {
+            if let Some(overlay_auth) = dn.and_then(|dn| self.credentials.get(&dn)) {
+                let mut overlay_auth = overlay_auth.clone();
+                overlay_auth.merge(std::mem::take(auth));
+                *auth = overlay_auth;
+            }
+        };
+        // the DNs depend on the whole config, compute them before borrowing the entities
+        let apps_dns = config.data.apps.iter().map(|app| app.as_ldap_dn(config)).collect::<Vec<_>>();
+        let users_dns = config.data.users.iter().map(|user| user.as_ldap_dn(config)).collect::<Vec<_>>();
+        for (app, dn) in config.data.apps.iter_mut().zip(apps_dns) {
+            apply(dn, &mut app.auth);
+        }
+        for (user, dn) in config.data.users.iter_mut().zip(users_dns) {
+            apply(dn, &mut user.auth);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ldap::{config::fixtures, dn::LDAPDN};
+
+    use super::CredentialsOverlay;
+
+    #[test]
+    fn test_credentials_overlay() {
+        let mut config = fixtures::config(r#"
+            [[users]]
+            uid = "user1"
+            password = "old_password"
+
+            [[users]]
+            uid = "user2"
+            password = "old_password"
+        "#);
+
+        let mut overlay = CredentialsOverlay::default();
+        overlay.set_password(LDAPDN::from_str("uid=user1,ou=users,dc=example,dc=com").unwrap(), "new_password");
+        overlay.set_password(LDAPDN::from_str("uid=removed,ou=users,dc=example,dc=com").unwrap(), "new_password");
+
+        // the overlay survives a round trip
+        let overlay = toml::from_str::<CredentialsOverlay>(&toml::to_string(&overlay).unwrap()).unwrap();
+        overlay.apply(&mut config);
+
+        assert_eq!(config.data.users.len(), 2);
+        assert_eq!(config.data.users[0].auth.password.as_deref(), Some("new_password"));
+        assert_eq!(config.data.users[1].auth.password.as_deref(), Some("old_password"));
+    }
+
+    #[tokio::test]
+    async fn test_credentials_overlay_store() {
+        let dir = std::env::temp_dir().join(format!("femtoldap-overlay-store-{}", rand::random::<u64>()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let mut overlay = CredentialsOverlay::default();
+        overlay.set_password(LDAPDN::from_str("uid=user1,ou=users,dc=example,dc=com").unwrap(), "new_password");
+        overlay.store(&dir.join("credentials.toml")).await.unwrap();
+
+        // the temporary file is renamed over the overlay, nothing else is left behind
+        let mut names = Vec::new();
+        let mut dir_reader = tokio::fs::read_dir(&dir).await.unwrap();
+        while let Some(entry) = dir_reader.next_entry().await.unwrap() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        assert_eq!(names, vec!["credentials.toml"]);
+        assert_eq!(CredentialsOverlay::load(&dir.join("credentials.toml")).await.unwrap(), overlay);
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+}
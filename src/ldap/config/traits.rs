@@ -5,9 +5,14 @@ pub trait AsLDAPAttributes {
     fn as_ldap_attributes(&self) -> LDAPAttributes;
 }
 
+pub trait NaturalKey {
+    /// Get the value identifying this entity whatever the layout, definitions sharing it are merged
+    fn natural_key(&self) -> Option<String>;
+}
+
 pub trait AugmentConfig {
     /// Get the LDAP DN of this entry
-    fn as_ldap_dn(&self, _base_dn: &LDAPDN) -> Option<LDAPDN>;
+    fn as_ldap_dn(&self, config: &super::Config) -> Option<LDAPDN>;
 
     /// Transform self into an LDAP entry
     fn as_ldap_entry(&self, config: &super::Config, entries: &[LDAPEntry]) -> Option<LDAPEntry>;
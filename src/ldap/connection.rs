@@ -0,0 +1,73 @@
+use std::{pin::Pin, task::{Context, Poll}};
+
+/// A client connection, which may be upgraded to TLS with StartTLS
+pub enum LDAPConnection<I> {
+    Plain(I),
+    Tls(Box<tokio_rustls::server::TlsStream<I>>),
+    /// Placeholder while the connection is being upgraded
+    Upgrading,
+}
+impl<I> LDAPConnection<I>
+where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + std::marker::Unpin
+{
+
+    pub const fn is_tls(&self) -> bool {
+        matches!(self, Self::Tls(..))
+    }
+
+    /// Perform a TLS handshake over a plain connection (RFC 4513 section 3)
+    pub async fn upgrade(&mut self, tls_acceptor: &tokio_rustls::TlsAcceptor) -> std::io::Result<()> {
+        match std::mem::replace(self, Self::Upgrading) {
+            Self::Plain(conn) => {
+                *self = Self::Tls(Box::new(tls_acceptor.accept(conn).await?));
+                Ok(())
+            },
+            connection => {
+                *self = connection;
+                Err(std::io::Error::other("Connection is not plain"))
+            },
+        }
+    }
+
+}
+impl<I> tokio::io::AsyncRead for LDAPConnection<I>
+where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + std::marker::Unpin
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut tokio::io::ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(conn) => Pin::new(conn).poll_read(cx, buf),
+            Self::Tls(conn) => Pin::new(conn).poll_read(cx, buf),
+            Self::Upgrading => Poll::Ready(Err(std::io::ErrorKind::NotConnected.into())),
+        }
+    }
+}
+impl<I> tokio::io::AsyncWrite for LDAPConnection<I>
+where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + std::marker::Unpin
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(conn) => Pin::new(conn).poll_write(cx, buf),
+            Self::Tls(conn) => Pin::new(conn).poll_write(cx, buf),
+            Self::Upgrading => Poll::Ready(Err(std::io::ErrorKind::NotConnected.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(conn) => Pin::new(conn).poll_flush(cx),
+            Self::Tls(conn) => Pin::new(conn).poll_flush(cx),
+            Self::Upgrading => Poll::Ready(Err(std::io::ErrorKind::NotConnected.into())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(conn) => Pin::new(conn).poll_shutdown(cx),
+            Self::Tls(conn) => Pin::new(conn).poll_shutdown(cx),
+            Self::Upgrading => Poll::Ready(Err(std::io::ErrorKind::NotConnected.into())),
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use std::{collections::{HashMap, HashSet}, sync::Arc};
+use std::{borrow::Cow, collections::{HashMap, HashSet}, sync::Arc};
 
-use super::{datatypes::CIString, dn::LDAPDN, entry::LDAPEntry};
+use super::{datatypes::CIString, dn::LDAPDN, entry::LDAPEntry, schema};
 
 #[derive(Default)]
 pub struct LDAPReadOnlyInMemoryDatabase {
@@ -10,29 +10,38 @@ pub struct LDAPReadOnlyInMemoryDatabase {
     // login entities, i.e. entities for which login is possible
     login_entries: HashMap<LDAPDN, Arc<LDAPEntry>>,
 
+    // all entities, by their normalized DN
+    normalized_dn_index: HashMap<Vec<u8>, Arc<LDAPEntry>>,
+
     // indexes
     attr_index_names: HashSet<CIString>,
     attr_eq_index: HashMap<(CIString, Vec<u8>), HashSet<Arc<LDAPEntry>>>,
     attr_ex_index: HashMap<CIString, HashSet<Arc<LDAPEntry>>>,
+    attr_ngram_index: HashMap<(CIString, String), HashSet<Arc<LDAPEntry>>>,
 
     // dn suffixes
     dn_suffixes_indexes: HashMap<LDAPDN, HashSet<Arc<LDAPEntry>>>,
+
+    // dn parents, i.e. direct children of a DN
+    dn_parents_indexes: HashMap<LDAPDN, HashSet<Arc<LDAPEntry>>>,
 }
 impl LDAPReadOnlyInMemoryDatabase {
 
-    pub fn from_entries(entries: impl IntoIterator<Item = LDAPEntry>) -> Self {
+    pub fn from_entries(entries: impl IntoIterator<Item = LDAPEntry>) -> anyhow::Result<Self> {
         let mut instance = Self::default();
         for mut entry in entries {
             entry.tidy();
-            instance.add_entry(Arc::new(entry));
+            instance.add_entry(Arc::new(entry))?;
         }
         instance.tidy();
-        instance
+        Ok(instance)
     }
 
-    pub fn add_entry(&mut self, entry: Arc<LDAPEntry>) {
-        // assert entry does not exist
-        assert!(!self.entries.contains_key(&entry.dn), "Entry already exists {entry:?}");
+    pub fn add_entry(&mut self, entry: Arc<LDAPEntry>) -> anyhow::Result<()> {
+        // check entry does not exist
+        if self.entries.contains_key(&entry.dn) {
+            return Err(anyhow::anyhow!("Entry already exists {}", entry.dn));
+        }
 
         // add to all entities
         self.entries.insert(entry.dn.clone(), entry.clone());
@@ -42,8 +51,13 @@ impl LDAPReadOnlyInMemoryDatabase {
             self.login_entries.insert(entry.dn.clone(), entry.clone());
         }
 
+        // add to normalized DNs
+        if let Some(normalized_dn) = schema::MatchingRule::DistinguishedName.normalize(entry.dn.to_string().as_bytes()) {
+            self.normalized_dn_index.insert(normalized_dn, entry.clone());
+        }
+
         // index attributes
-        const INDEXED_ATTRIBUTES: &'static [&'static str] = &[
+        const INDEXED_ATTRIBUTES: &[&str] = &[
             "cn",
             "mail",
             "mailAlias",
@@ -59,11 +73,23 @@ impl LDAPReadOnlyInMemoryDatabase {
                 self.attr_index_names.insert(CIString::new(indexed_attribute));
 
                 // index attribute values
+                let attribute_type = schema::attribute_type(&CIString::new(indexed_attribute));
                 for attribute_value in attribute.values.iter() {
-                    // attribute equality index
-                    self.attr_eq_index.entry((CIString::new(indexed_attribute), attribute_value.to_vec()))
-                        .or_default()
-                        .insert(entry.clone());
+                    // attribute equality index, by normalized value
+                    if let Some(attribute_value) = attribute_type.normalize(attribute_value) {
+                        self.attr_eq_index.entry((CIString::new(indexed_attribute), attribute_value))
+                            .or_default()
+                            .insert(entry.clone());
+                    }
+
+                    // attribute substring index, by trigrams of the normalized value
+                    if let Some(attribute_value) = attribute_type.substring.and_then(|rule| rule.normalize(attribute_value)) {
+                        for ngram in ngrams(&attribute_value) {
+                            self.attr_ngram_index.entry((CIString::new(indexed_attribute), ngram))
+                                .or_default()
+                                .insert(entry.clone());
+                        }
+                    }
 
                     // attribute existence index
                     self.attr_ex_index.entry(CIString::new(indexed_attribute))
@@ -80,118 +106,319 @@ impl LDAPReadOnlyInMemoryDatabase {
                 .or_default()
                 .insert(entry.clone());
         }
+
+        // index parents
+        if let Some(parent) = entry.dn.parent() {
+            self.dn_parents_indexes.entry(parent)
+                .or_default()
+                .insert(entry.clone());
+        }
+
+        Ok(())
     }
 
-    pub fn do_bind(&self, dn: &LDAPDN, password: impl AsRef<str>) ->  Option<Arc<LDAPEntry>> {
-        if let Some(entry) = self.login_entries.get(dn) {
-            if entry.attributes.check_password(password) {
-                return Some(entry.clone());
+    /// Whether a DN exists, either as an entry or as the parent of some entries
+    pub fn contains(&self, dn: &LDAPDN) -> bool {
+        dn.is_empty() || self.entries.contains_key(dn) || self.dn_suffixes_indexes.contains_key(dn)
+    }
+
+    /// The DN as stored for a DN which only differs by case or spacing, e.g. `UID=alice,OU=users`
+    ///
+    /// RDNs under the longest matching ancestor are kept as given.
+    pub fn resolve_dn(&self, dn: &LDAPDN) -> LDAPDN {
+        let mut ancestor = Some(dn.clone());
+        let mut depth = 0;
+        while let Some(candidate) = ancestor {
+            if let Some(entry) = self.normalized_dn_index.get(schema::normalize_dn(&candidate).to_string().as_bytes()) {
+                return dn.iter().take(depth).chain(entry.dn.iter()).cloned().collect();
+            }
+            ancestor = candidate.parent();
+            depth += 1;
+        }
+        dn.clone()
+    }
+
+    /// The longest ancestor of a DN which is an entry
+    pub fn matched_dn(&self, dn: &LDAPDN) -> LDAPDN {
+        let mut ancestor = dn.parent();
+        while let Some(dn) = ancestor {
+            if self.entries.contains_key(&dn) {
+                return dn;
             }
+            ancestor = dn.parent();
+        }
+        LDAPDN::empty()
+    }
+
+    pub fn entry(&self, dn: &LDAPDN) -> Option<&Arc<LDAPEntry>> {
+        self.entries.get(dn)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Arc<LDAPEntry>> {
+        self.entries.values()
+    }
+
+    /// An entry which may bind
+    pub fn login_entry(&self, dn: &LDAPDN) -> Option<&Arc<LDAPEntry>> {
+        self.login_entries.get(dn)
+    }
+
+    /// Check the credentials of a login entity, this is slow with hashed passwords
+    pub fn do_bind(&self, dn: &LDAPDN, password: impl AsRef<str>) ->  Option<Arc<LDAPEntry>> {
+        match self.login_entries.get(dn) {
+            Some(entry) if entry.attributes.check_password(password.as_ref()) => Some(entry.clone()),
+            Some(_) => None,
+            None => {
+                // take as long as for an existing entity, not to reveal which ones exist
+                super::password::verify_dummy_password(password.as_ref());
+                None
+            },
         }
-        None
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn search(&self, base_dn: &LDAPDN, filter: &ldap3_proto::LdapFilter) -> HashSet<Arc<LDAPEntry>> {
-        if let Some(entry) = self.entries.get(&base_dn) {
-            // single entry lookup
-            if entry.matches_filter(filter) {
-                HashSet::from([ entry.clone() ])
+    pub fn search(&self, base_dn: &LDAPDN, scope: &ldap3_proto::LdapSearchScope, filter: &ldap3_proto::LdapFilter) -> HashSet<Arc<LDAPEntry>> {
+        let candidates = self.scoped_entries(base_dn, scope);
+        if candidates.is_empty() {
+            HashSet::with_capacity(0)
+        } else {
+            self.filter_entries(&candidates, filter)
+        }
+    }
+
+    /// Get the entries which are in the scope of a search
+    fn scoped_entries(&self, base_dn: &LDAPDN, scope: &ldap3_proto::LdapSearchScope) -> Cow<'_, HashSet<Arc<LDAPEntry>>> {
+        match scope {
+            // the base entry only
+            ldap3_proto::LdapSearchScope::Base => if let Some(entry) = self.entries.get(base_dn) {
+                Cow::Owned(HashSet::from([ entry.clone() ]))
             } else {
-                HashSet::with_capacity(0)
-            }
-        } else if let Some(entries) = self.dn_suffixes_indexes.get(base_dn) {
-            // suffix search
-            match filter {
-                // not
-                ldap3_proto::LdapFilter::Not(ldap_filter) => {
-                    let excluded_entries = self.search(base_dn, ldap_filter);
-                    entries.difference(&excluded_entries).cloned().collect()
-                },
-                // and
-                ldap3_proto::LdapFilter::And(ldap_filters) => if ldap_filters.is_empty() {
-                    HashSet::with_capacity(0)
-                } else {
+                Cow::Owned(HashSet::with_capacity(0))
+            },
+            // direct children of the base
+            ldap3_proto::LdapSearchScope::OneLevel => if let Some(entries) = self.dn_parents_indexes.get(base_dn) {
+                Cow::Borrowed(entries)
+            } else {
+                Cow::Owned(HashSet::with_capacity(0))
+            },
+            // the base and all of its descendants (the root DSE is only reachable with a base search)
+            ldap3_proto::LdapSearchScope::Subtree => match (self.entries.get(base_dn), self.dn_suffixes_indexes.get(base_dn)) {
+                (Some(entry), Some(entries)) if !base_dn.is_empty() => {
                     let mut entries = entries.clone();
-                    for sub_filter in ldap_filters {
-                        let sub_entries = self.search(base_dn, sub_filter);
-                        entries = entries.intersection(&sub_entries).cloned().collect();
-                        if entries.is_empty() {
-                            break
-                        }
-                    }
-                    entries
+                    entries.insert(entry.clone());
+                    Cow::Owned(entries)
                 },
-                // or
-                ldap3_proto::LdapFilter::Or(ldap_filters) => if ldap_filters.is_empty() {
-                    HashSet::with_capacity(0)
-                } else {
-                    ldap_filters.iter()
-                        .map(|sub_filter| self.search(base_dn, sub_filter))
-                        .reduce(|mut acc, mut elem| {
-                            acc.extend(elem.drain());
-                            acc
-                        })
-                        .unwrap()
-                },
-                // equality
-                ldap3_proto::LdapFilter::Equality(attribute_name, attribute_value) => {
-                    let attribute_name = CIString::new(attribute_name);
-                    if self.attr_index_names.contains(&attribute_name) {
-                        if let Some(filtered) = self.attr_eq_index.get(&(attribute_name, attribute_value.as_bytes().to_vec())) {
-                            entries.intersection(filtered).cloned().collect()
-                        } else {
-                            HashSet::with_capacity(0)
-                        }
-                    } else {
-                        entries.iter()
-                            .filter(|entity| if let Some(attribute) = entity.attributes.get_attribute(&attribute_name) {
-                                attribute.values.iter()
-                                    .any(|value| attribute_value.as_bytes() == value)
-                            } else {
-                                false
-                            })
-                            .cloned()
-                            .collect()
+                (Some(entry), None) if !base_dn.is_empty() => Cow::Owned(HashSet::from([ entry.clone() ])),
+                (_, Some(entries)) => Cow::Borrowed(entries),
+                (_, None) => Cow::Owned(HashSet::with_capacity(0)),
+            },
+            // all descendants of the base, excluding the base itself
+            ldap3_proto::LdapSearchScope::Children => if let Some(entries) = self.dn_suffixes_indexes.get(base_dn) {
+                Cow::Borrowed(entries)
+            } else {
+                Cow::Owned(HashSet::with_capacity(0))
+            },
+        }
+    }
+
+    /// Select the entries matching a filter out of a set of candidates
+    fn filter_entries(&self, entries: &HashSet<Arc<LDAPEntry>>, filter: &ldap3_proto::LdapFilter) -> HashSet<Arc<LDAPEntry>> {
+        match filter {
+            // not
+            ldap3_proto::LdapFilter::Not(ldap_filter) => {
+                let excluded_entries = self.filter_entries(entries, ldap_filter);
+                entries.difference(&excluded_entries).cloned().collect()
+            },
+            // and
+            ldap3_proto::LdapFilter::And(ldap_filters) => if ldap_filters.is_empty() {
+                HashSet::with_capacity(0)
+            } else {
+                let mut entries = entries.clone();
+                for sub_filter in ldap_filters {
+                    entries = self.filter_entries(&entries, sub_filter);
+                    if entries.is_empty() {
+                        break
                     }
-                },
-                // present
-                ldap3_proto::LdapFilter::Present(attribute) => {
-                    let attribute_name = CIString::new(attribute);
-                    if self.attr_index_names.contains(&attribute_name) {
-                        if let Some(filtered) = self.attr_ex_index.get(&attribute_name) {
-                            entries.intersection(filtered).cloned().collect()
+                }
+                entries
+            },
+            // or
+            ldap3_proto::LdapFilter::Or(ldap_filters) => if ldap_filters.is_empty() {
+                HashSet::with_capacity(0)
+            } else {
+                ldap_filters.iter()
+                    .map(|sub_filter| self.filter_entries(entries, sub_filter))
+                    .reduce(|mut acc, mut elem| {
+                        acc.extend(elem.drain());
+                        acc
+                    })
+                    .unwrap()
+            },
+            // equality
+            ldap3_proto::LdapFilter::Equality(attribute_name, attribute_value) => {
+                let attribute_name = CIString::new(attribute_name);
+                if self.attr_index_names.contains(&attribute_name) {
+                    self.lookup_eq_index(entries, attribute_name, attribute_value)
+                } else {
+                    let attribute_type = schema::attribute_type(&attribute_name);
+                    entries.iter()
+                        .filter(|entity| if let Some(attribute) = entity.attributes.get_attribute(&attribute_name) {
+                            attribute.values.iter()
+                                .any(|value| attribute_type.matches(value, attribute_value.as_bytes()))
                         } else {
-                            HashSet::with_capacity(0)
-                        }
+                            false
+                        })
+                        .cloned()
+                        .collect()
+                }
+            },
+            // present
+            ldap3_proto::LdapFilter::Present(attribute) => {
+                let attribute_name = CIString::new(attribute);
+                if self.attr_index_names.contains(&attribute_name) {
+                    if let Some(filtered) = self.attr_ex_index.get(&attribute_name) {
+                        entries.intersection(filtered).cloned().collect()
                     } else {
-                        entries.iter()
-                            .filter(|entity| if let Some(..) = entity.attributes.get_attribute(&attribute_name) {
-                                true
-                            } else {
-                                false
-                            })
-                            .cloned()
-                            .collect()
+                        HashSet::with_capacity(0)
                     }
-                },
-                // substring
-                ldap3_proto::LdapFilter::Substring(..) => {
+                } else {
                     entries.iter()
-                        .filter(|entry| entry.matches_filter(filter))
+                        .filter(|entity| entity.attributes.get_attribute(&attribute_name).is_some())
                         .cloned()
                         .collect()
-                },
-                f => {
-                    tracing::warn!(filter = ?f, "Unsupported filter");
-                    HashSet::with_capacity(0)
-                },
-            }
+                }
+            },
+            // extensible match following the chain of DN values
+            ldap3_proto::LdapFilter::Extensible(assertion) if Self::is_in_chain(assertion) => {
+                self.lookup_in_chain(entries, assertion)
+            },
+            // extensible match with the attribute equality, optionally on DN components
+            ldap3_proto::LdapFilter::Extensible(assertion) if self.is_eq_index_usable(assertion) => {
+                let attribute_name = CIString::new(assertion.type_.as_ref().unwrap());
+                let mut found = self.lookup_eq_index(entries, attribute_name, &assertion.match_value);
+                if assertion.dn_attributes {
+                    found.extend(
+                        entries.iter()
+                            .filter(|entry| !found.contains(*entry))
+                            .filter(|entry| entry.matches_extensible(assertion))
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    );
+                }
+                found
+            },
+            // substring
+            ldap3_proto::LdapFilter::Substring(attribute_name, substring_filter) => {
+                let attribute_name = CIString::new(attribute_name);
+                let candidates = if self.attr_index_names.contains(&attribute_name) {
+                    self.lookup_ngram_index(entries, attribute_name, substring_filter)
+                } else {
+                    None
+                };
+                candidates.as_ref().unwrap_or(entries).iter()
+                    .filter(|entry| entry.matches_filter(filter))
+                    .cloned()
+                    .collect()
+            },
+            // ordering, approximate and extensible
+            ldap3_proto::LdapFilter::GreaterOrEqual(..) | ldap3_proto::LdapFilter::LessOrEqual(..) | ldap3_proto::LdapFilter::Approx(..) | ldap3_proto::LdapFilter::Extensible(..) => {
+                entries.iter()
+                    .filter(|entry| entry.matches_filter(filter))
+                    .cloned()
+                    .collect()
+            },
+        }
+    }
+
+    /// Select the entries whose attribute equals a value, using the equality index
+    fn lookup_eq_index(&self, entries: &HashSet<Arc<LDAPEntry>>, attribute_name: CIString, attribute_value: &str) -> HashSet<Arc<LDAPEntry>> {
+        let Some(attribute_value) = schema::attribute_type(&attribute_name).normalize(attribute_value.as_bytes()) else {
+            return HashSet::with_capacity(0);
+        };
+        if let Some(filtered) = self.attr_eq_index.get(&(attribute_name, attribute_value)) {
+            entries.intersection(filtered).cloned().collect()
         } else {
             HashSet::with_capacity(0)
         }
     }
 
+    /// Narrow down the candidates of a substring filter using the trigram index,
+    /// `None` if the filter has no component long enough to be looked up
+    fn lookup_ngram_index(&self, entries: &HashSet<Arc<LDAPEntry>>, attribute_name: CIString, substring_filter: &ldap3_proto::proto::LdapSubstringFilter) -> Option<HashSet<Arc<LDAPEntry>>> {
+        let substring_rule = schema::attribute_type(&attribute_name).substring?;
+        let mut candidates: Option<HashSet<Arc<LDAPEntry>>> = None;
+        let components = substring_filter.initial.iter()
+            .chain(substring_filter.any.iter())
+            .chain(substring_filter.final_.iter());
+        for component in components {
+            let component = substring_rule.normalize(component.as_bytes())?;
+            for ngram in ngrams(&component) {
+                let Some(filtered) = self.attr_ngram_index.get(&(attribute_name.clone(), ngram)) else {
+                    return Some(HashSet::with_capacity(0));
+                };
+                let narrowed = candidates.as_ref().unwrap_or(entries).intersection(filtered).cloned().collect::<HashSet<_>>();
+                if narrowed.is_empty() {
+                    return Some(narrowed);
+                }
+                candidates = Some(narrowed);
+            }
+        }
+        candidates
+    }
+
+    /// Whether an extensible match uses LDAP_MATCHING_RULE_IN_CHAIN on an attribute
+    fn is_in_chain(assertion: &ldap3_proto::proto::LdapMatchingRuleAssertion) -> bool {
+        assertion.type_.is_some() && assertion.matching_rule.as_deref().and_then(schema::MatchingRule::from_name_or_oid) == Some(schema::MatchingRule::InChain)
+    }
+
+    /// Select the entries from which the assertion DN is reached following the DN values of the attribute, e.g.
+    /// `memberOf` for all the groups containing an entry and `member` for all the entries contained in a group
+    fn lookup_in_chain(&self, entries: &HashSet<Arc<LDAPEntry>>, assertion: &ldap3_proto::proto::LdapMatchingRuleAssertion) -> HashSet<Arc<LDAPEntry>> {
+        let attribute_name = CIString::new(assertion.type_.as_ref().unwrap());
+        let normalize = |value: &[u8]| schema::MatchingRule::InChain.normalize(value);
+        let Some(target) = normalize(assertion.match_value.as_bytes()) else {
+            return HashSet::with_capacity(0);
+        };
+        let reaches_target = |entry: &LDAPEntry| {
+            let mut visited = HashSet::new();
+            let mut pending = vec![entry];
+            while let Some(entry) = pending.pop() {
+                let Some(attribute) = entry.attributes.get_attribute(&attribute_name) else {
+                    continue;
+                };
+                for value in attribute.values.iter() {
+                    let Some(normalized) = normalize(value) else {
+                        continue;
+                    };
+                    if normalized == target {
+                        return true;
+                    }
+                    if let Some(next) = self.normalized_dn_index.get(&normalized) && visited.insert(normalized) {
+                        pending.push(next);
+                    }
+                }
+            }
+            false
+        };
+        entries.iter()
+            .filter(|entry| reaches_target(entry))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether an extensible match can be answered by the equality index
+    fn is_eq_index_usable(&self, assertion: &ldap3_proto::proto::LdapMatchingRuleAssertion) -> bool {
+        let Some(attribute_name) = assertion.type_.as_ref().map(CIString::new) else {
+            return false;
+        };
+        let attribute_type = schema::attribute_type(&attribute_name);
+        let same_rule = match assertion.matching_rule.as_ref() {
+            None => true,
+            Some(name) => schema::MatchingRule::from_name_or_oid(name) == Some(attribute_type.equality),
+        };
+        same_rule && self.attr_index_names.contains(&attribute_name)
+    }
+
     pub fn tidy(&mut self) {
         // shrink arrays
         self.entries.shrink_to_fit();
@@ -199,10 +426,24 @@ impl LDAPReadOnlyInMemoryDatabase {
         self.attr_index_names.shrink_to_fit();
         self.attr_eq_index.shrink_to_fit();
         self.attr_ex_index.shrink_to_fit();
+        self.attr_ngram_index.shrink_to_fit();
         self.dn_suffixes_indexes.shrink_to_fit();
+        self.dn_parents_indexes.shrink_to_fit();
     }
 
 }
+/// Trigrams of a string, used by the substring index
+fn ngrams(s: &str) -> Vec<String> {
+    const NGRAM_LENGTH: usize = 3;
+    let boundaries = s.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(s.len()))
+        .collect::<Vec<_>>();
+    boundaries.windows(NGRAM_LENGTH + 1)
+        .map(|window| s[window[0]..window[NGRAM_LENGTH]].to_string())
+        .collect()
+}
+
 impl std::fmt::Debug for LDAPReadOnlyInMemoryDatabase {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("LDAPReadOnlyInMemoryDatabase\n")?;
@@ -219,7 +460,7 @@ impl std::fmt::Debug for LDAPReadOnlyInMemoryDatabase {
         // attribute value indexes
         writeln!(f, "  Attribute value index ({} entries)", self.attr_eq_index.len())?;
         for ((attr_name, attr_value), entries) in self.attr_eq_index.iter() {
-            let attr_value = String::from_utf8_lossy(&attr_value);
+            let attr_value = String::from_utf8_lossy(attr_value);
             writeln!(f, "    {attr_name}={attr_value} ({} entries)", entries.len())?;
             for (i, entry) in entries.iter().enumerate() {
                 writeln!(f, "      [{}] => {}", i + 1, entry.dn)?;
@@ -246,6 +487,251 @@ impl std::fmt::Debug for LDAPReadOnlyInMemoryDatabase {
                 writeln!(f, "      [{}] => {}", i + 1, entry.dn)?;
             }
         }
+        // dn parents
+        writeln!(f, "  DN parent index ({} entries)", self.dn_parents_indexes.len())?;
+        let mut parents = self.dn_parents_indexes.keys().collect::<Vec<_>>();
+        parents.sort_unstable();
+        for dn in parents {
+            writeln!(f, "    {dn}")?;
+            for (i, entry) in self.dn_parents_indexes.get(dn).unwrap().iter().enumerate() {
+                writeln!(f, "      [{}] => {}", i + 1, entry.dn)?;
+            }
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ldap::{attributes::LDAPAttributes, config::fixtures, dn::LDAPDN, entry::LDAPEntry};
+
+    use super::LDAPReadOnlyInMemoryDatabase;
+
+    fn test_database() -> LDAPReadOnlyInMemoryDatabase {
+        LDAPReadOnlyInMemoryDatabase::from_entries(
+            [
+                "",
+                "dc=example,dc=com",
+                "ou=users,dc=example,dc=com",
+                "uid=a,ou=users,dc=example,dc=com",
+                "uid=b,ou=users,dc=example,dc=com",
+                "cn=nested,uid=b,ou=users,dc=example,dc=com",
+            ]
+            .into_iter()
+            .map(|dn| {
+                let mut attributes = LDAPAttributes::default();
+                attributes.add_value("objectClass", "top");
+                LDAPEntry::new(LDAPDN::from_str(dn).unwrap(), attributes)
+            })
+        ).unwrap()
+    }
+
+    fn search(database: &LDAPReadOnlyInMemoryDatabase, base_dn: &str, scope: ldap3_proto::LdapSearchScope) -> Vec<String> {
+        search_filter(database, base_dn, scope, ldap3_proto::LdapFilter::Present("objectClass".to_string()))
+    }
+
+    fn search_filter(database: &LDAPReadOnlyInMemoryDatabase, base_dn: &str, scope: ldap3_proto::LdapSearchScope, filter: ldap3_proto::LdapFilter) -> Vec<String> {
+        let mut dns = database.search(&LDAPDN::from_str(base_dn).unwrap(), &scope, &filter)
+            .into_iter()
+            .map(|entry| entry.dn.to_string())
+            .collect::<Vec<_>>();
+        dns.sort_unstable();
+        dns
+    }
+
+    #[test]
+    fn test_search_scopes() {
+        let database = test_database();
+
+        // base
+        assert_eq!(search(&database, "ou=users,dc=example,dc=com", ldap3_proto::LdapSearchScope::Base), vec![
+            "ou=users,dc=example,dc=com",
+        ]);
+        assert_eq!(search(&database, "", ldap3_proto::LdapSearchScope::Base), vec![
+            "<root DSE>",
+        ]);
+        assert!(search(&database, "ou=missing,dc=example,dc=com", ldap3_proto::LdapSearchScope::Base).is_empty());
+
+        // one level
+        assert_eq!(search(&database, "ou=users,dc=example,dc=com", ldap3_proto::LdapSearchScope::OneLevel), vec![
+            "uid=a,ou=users,dc=example,dc=com",
+            "uid=b,ou=users,dc=example,dc=com",
+        ]);
+
+        // subtree
+        assert_eq!(search(&database, "ou=users,dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree), vec![
+            "cn=nested,uid=b,ou=users,dc=example,dc=com",
+            "ou=users,dc=example,dc=com",
+            "uid=a,ou=users,dc=example,dc=com",
+            "uid=b,ou=users,dc=example,dc=com",
+        ]);
+        assert_eq!(search(&database, "uid=a,ou=users,dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree), vec![
+            "uid=a,ou=users,dc=example,dc=com",
+        ]);
+
+        // subordinate subtree
+        assert_eq!(search(&database, "ou=users,dc=example,dc=com", ldap3_proto::LdapSearchScope::Children), vec![
+            "cn=nested,uid=b,ou=users,dc=example,dc=com",
+            "uid=a,ou=users,dc=example,dc=com",
+            "uid=b,ou=users,dc=example,dc=com",
+        ]);
+        assert!(search(&database, "uid=a,ou=users,dc=example,dc=com", ldap3_proto::LdapSearchScope::Children).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_entries() {
+        let config = fixtures::config(r#"
+            [[users]]
+            uid = "user"
+
+            [[users]]
+            uid = "user"
+        "#);
+        assert!(LDAPReadOnlyInMemoryDatabase::from_entries(config.assemble_entries()).is_err());
+    }
+
+    #[test]
+    fn test_search_extensible_dn_attributes() {
+        let database = test_database();
+        let filter = |type_: &str, match_value: &str| ldap3_proto::LdapFilter::Extensible(ldap3_proto::proto::LdapMatchingRuleAssertion {
+            matching_rule: None,
+            type_: Some(type_.to_string()),
+            match_value: match_value.to_string(),
+            dn_attributes: true,
+        });
+
+        // per-entry path
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, filter("ou", "users")), vec![
+            "cn=nested,uid=b,ou=users,dc=example,dc=com",
+            "ou=users,dc=example,dc=com",
+            "uid=a,ou=users,dc=example,dc=com",
+            "uid=b,ou=users,dc=example,dc=com",
+        ]);
+
+        // index-assisted path
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, filter("uid", "b")), vec![
+            "cn=nested,uid=b,ou=users,dc=example,dc=com",
+            "uid=b,ou=users,dc=example,dc=com",
+        ]);
+    }
+
+    #[test]
+    fn test_search_case_insensitive_equality() {
+        let config = fixtures::config(r#"
+            [[groups]]
+            name = "group"
+
+            [[users]]
+            uid = "sample_user"
+            mail = "sample_user@example.com"
+            group_names = [ "group" ]
+        "#);
+        let database = LDAPReadOnlyInMemoryDatabase::from_entries(config.assemble_entries()).unwrap();
+        let equality = |k: &str, v: &str| ldap3_proto::LdapFilter::Equality(k.to_string(), v.to_string());
+
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, equality("uid", "Sample_User")), vec![
+            "uid=sample_user,ou=users,dc=example,dc=com",
+        ]);
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, equality("MAIL", "SAMPLE_USER@example.com")), vec![
+            "uid=sample_user,ou=users,dc=example,dc=com",
+        ]);
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, equality("uniqueMember", "UID=Sample_User,ou=users,dc=example,dc=com")), vec![
+            "cn=group,ou=groups,dc=example,dc=com",
+        ]);
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, equality("objectClass", "INETORGPERSON")), vec![
+            "uid=sample_user,ou=users,dc=example,dc=com",
+        ]);
+    }
+
+    #[test]
+    fn test_search_substring() {
+        let config = fixtures::config(r#"
+            [[users]]
+            uid = "a.b"
+            mail = "a.b+tag@example.com"
+
+            [[users]]
+            uid = "axb"
+            mail = "axb@example.org"
+        "#);
+        let database = LDAPReadOnlyInMemoryDatabase::from_entries(config.assemble_entries()).unwrap();
+        let substring = |k: &str, v: &str| ldap3_proto::LdapFilter::Substring(k.to_string(), ldap3_proto::proto::LdapSubstringFilter::from(v));
+
+        // indexed
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, substring("mail", "*@EXAMPLE.com")), vec![
+            "uid=a.b,ou=users,dc=example,dc=com",
+        ]);
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, substring("uid", "a.b*")), vec![
+            "uid=a.b,ou=users,dc=example,dc=com",
+        ]);
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, substring("mail", "a*@example.*")), vec![
+            "uid=a.b,ou=users,dc=example,dc=com",
+            "uid=axb,ou=users,dc=example,dc=com",
+        ]);
+
+        // not indexed
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, substring("homeDirectory", "/home/a.*")), vec![
+            "uid=a.b,ou=users,dc=example,dc=com",
+        ]);
+    }
+
+    #[test]
+    fn test_search_in_chain() {
+        let config = fixtures::config(r#"
+            [[groups]]
+            name = "admins"
+
+            [[groups]]
+            name = "staff"
+            member_groups = [ "admins" ]
+
+            [[groups]]
+            name = "everyone"
+            member_groups = [ "staff" ]
+
+            [[users]]
+            uid = "alice"
+            group_names = [ "admins" ]
+
+            [[users]]
+            uid = "bob"
+            group_names = [ "staff" ]
+        "#);
+        // member values are compared as DNs, not as strings
+        let mut outsiders = LDAPAttributes::default();
+        outsiders.add_value("objectClass", "groupOfUniqueNames");
+        outsiders.add_value("uniqueMember", "CN=Everyone,OU=Groups,DC=Example,DC=Com");
+        let outsiders = LDAPEntry::new(LDAPDN::from_str("cn=outsiders,ou=groups,dc=example,dc=com").unwrap(), outsiders);
+        let database = LDAPReadOnlyInMemoryDatabase::from_entries(config.assemble_entries().into_iter().chain([outsiders])).unwrap();
+        let in_chain = |k: &str, v: &str| ldap3_proto::LdapFilter::Extensible(ldap3_proto::proto::LdapMatchingRuleAssertion {
+            matching_rule: Some("1.2.840.113556.1.4.1941".to_string()),
+            type_: Some(k.to_string()),
+            match_value: v.to_string(),
+            dn_attributes: false,
+        });
+
+        // everything within a group
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, in_chain("memberOf", "cn=everyone,ou=groups,dc=example,dc=com")), vec![
+            "cn=admins,ou=groups,dc=example,dc=com",
+            "cn=staff,ou=groups,dc=example,dc=com",
+            "uid=alice,ou=users,dc=example,dc=com",
+            "uid=bob,ou=users,dc=example,dc=com",
+        ]);
+
+        // every group containing an entry
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, in_chain("uniqueMember", "UID=Alice,ou=users,dc=example,dc=com")), vec![
+            "cn=admins,ou=groups,dc=example,dc=com",
+            "cn=everyone,ou=groups,dc=example,dc=com",
+            "cn=outsiders,ou=groups,dc=example,dc=com",
+            "cn=staff,ou=groups,dc=example,dc=com",
+        ]);
+        assert_eq!(search_filter(&database, "dc=example,dc=com", ldap3_proto::LdapSearchScope::Subtree, in_chain("uniqueMember", "uid=bob,ou=users,dc=example,dc=com")), vec![
+            "cn=everyone,ou=groups,dc=example,dc=com",
+            "cn=outsiders,ou=groups,dc=example,dc=com",
+            "cn=staff,ou=groups,dc=example,dc=com",
+        ]);
+    }
+
+}
@@ -23,7 +23,7 @@ impl PartialEq for CIString {
 impl Eq for CIString {}
 impl PartialOrd for CIString {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.lower.partial_cmp(&other.lower)
+        Some(self.cmp(other))
     }
 }
 impl Ord for CIString {
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use super::{attributes::LDAPAttributes, database::LDAPReadOnlyInMemoryDatabase, datatypes::CIString, dn::LDAPDN};
+
+/// Attributes whose values are never reported
+const REDACTED_ATTRIBUTES: &[&str] = &["userPassword"];
+
+/// Attributes expected to change on every reload
+const IGNORED_ATTRIBUTES: &[&str] = &["femtoldapReloadGeneration", "femtoldapReloadTimestamp"];
+
+/// Changes between two generations of the database
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LDAPDatabaseDiff {
+    pub added: Vec<LDAPDN>,
+    pub removed: Vec<LDAPDN>,
+    pub modified: BTreeMap<LDAPDN, Vec<LDAPAttributeDiff>>,
+}
+impl LDAPDatabaseDiff {
+
+    pub fn new(old: &LDAPReadOnlyInMemoryDatabase, new: &LDAPReadOnlyInMemoryDatabase) -> Self {
+        let mut diff = Self::default();
+        for new_entry in new.entries() {
+            match old.entry(&new_entry.dn) {
+                Some(old_entry) => {
+                    let attribute_diffs = LDAPAttributeDiff::between(&old_entry.attributes, &new_entry.attributes);
+                    if !attribute_diffs.is_empty() {
+                        diff.modified.insert(new_entry.dn.clone(), attribute_diffs);
+                    }
+                },
+                None => diff.added.push(new_entry.dn.clone()),
+            }
+        }
+        diff.removed = old.entries()
+            .filter(|old_entry| new.entry(&old_entry.dn).is_none())
+            .map(|old_entry| old_entry.dn.clone())
+            .collect();
+        diff.added.sort();
+        diff.removed.sort();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+}
+
+/// Values added to and removed from an attribute, sensitive values are redacted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LDAPAttributeDiff {
+    pub name: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+impl LDAPAttributeDiff {
+
+    fn between(old: &LDAPAttributes, new: &LDAPAttributes) -> Vec<Self> {
+        let mut names = old.iter().chain(new.iter())
+            .map(|attribute| CIString::new(&attribute.name))
+            .filter(|name| !IGNORED_ATTRIBUTES.iter().any(|ignored| CIString::new(ignored) == *name))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        names.into_iter()
+            .filter_map(|name| {
+                let old_values = old.get_attribute(&name).map(|attribute| attribute.values.as_slice()).unwrap_or_default();
+                let new_values = new.get_attribute(&name).map(|attribute| attribute.values.as_slice()).unwrap_or_default();
+                let redacted = REDACTED_ATTRIBUTES.iter().any(|redacted| CIString::new(redacted) == name);
+                let describe = |value: &Vec<u8>| if redacted { "<redacted>".to_string() } else { String::from_utf8_lossy(value).into_owned() };
+                let attribute_diff = Self {
+                    name: new.get_attribute(&name).or(old.get_attribute(&name)).map(|attribute| attribute.name.clone()).unwrap_or_default(),
+                    added: new_values.iter().filter(|value| !old_values.contains(value)).map(describe).collect(),
+                    removed: old_values.iter().filter(|value| !new_values.contains(value)).map(describe).collect(),
+                };
+                (!attribute_diff.added.is_empty() || !attribute_diff.removed.is_empty()).then_some(attribute_diff)
+            })
+            .collect()
+    }
+
+}
+impl std::fmt::Display for LDAPAttributeDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        for value in self.added.iter() {
+            write!(f, " +{value:?}")?;
+        }
+        for value in self.removed.iter() {
+            write!(f, " -{value:?}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ldap::{config::fixtures, database::LDAPReadOnlyInMemoryDatabase, dn::LDAPDN};
+
+    use super::LDAPDatabaseDiff;
+
+    fn database(items: &str) -> LDAPReadOnlyInMemoryDatabase {
+        LDAPReadOnlyInMemoryDatabase::from_entries(fixtures::config(items).assemble_entries()).unwrap()
+    }
+
+    #[test]
+    fn test_database_diff() {
+        let old = database(r#"
+            [[users]]
+            uid = "user1"
+            password = "old_password"
+            mail = "user1@example.com"
+
+            [[users]]
+            uid = "user2"
+        "#);
+        let new = database(r#"
+            [[users]]
+            uid = "user1"
+            password = "new_password"
+            mail = "user1@example.org"
+
+            [[users]]
+            uid = "user3"
+        "#);
+
+        let diff = LDAPDatabaseDiff::new(&old, &new);
+        assert_eq!(diff.added, vec![LDAPDN::from_str("uid=user3,ou=users,dc=example,dc=com").unwrap()]);
+        assert_eq!(diff.removed, vec![LDAPDN::from_str("uid=user2,ou=users,dc=example,dc=com").unwrap()]);
+        let changes = diff.modified.iter()
+            .flat_map(|(dn, attribute_diffs)| attribute_diffs.iter().map(move |attribute_diff| format!("{dn}: {attribute_diff}")))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, vec![
+            r#"uid=user1,ou=users,dc=example,dc=com: mail +"user1@example.org" -"user1@example.com""#,
+            r#"uid=user1,ou=users,dc=example,dc=com: userPassword +"<redacted>" -"<redacted>""#,
+        ]);
+
+        assert!(LDAPDatabaseDiff::new(&new, &new).is_empty());
+    }
+
+}
@@ -26,6 +26,17 @@ impl LDAPDN {
         self
     }
 
+    //
+    // Accessors
+    //
+    pub fn parent(&self) -> Option<LDAPDN> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Self(self.iter().skip(1).cloned().collect()))
+        }
+    }
+
     //
     // Matchers
     //
@@ -67,9 +78,9 @@ impl std::fmt::Display for LDAPDN {
                 if i != 0 {
                     f.write_str(",")?;
                 }
-                f.write_str(&k)?;
+                f.write_str(k)?;
                 f.write_str("=")?;
-                f.write_str(&v)?;
+                f.write_str(v)?;
             }
             Ok(())
         }
@@ -128,7 +139,7 @@ impl serde::Serialize for LDAPDN {
 impl Mergeable<Self> for LDAPDN {
     fn merge(&mut self, mut other: Self) {
         if self.is_empty() && !other.is_empty() {
-            let _ = std::mem::swap(self, &mut other);
+            std::mem::swap(self, &mut other);
         }
     }
 }
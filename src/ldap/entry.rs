@@ -1,10 +1,12 @@
-use super::{acl::LDAPACL, attributes::LDAPAttributes, datatypes::CIString, dn::LDAPDN, traits::Mergeable};
+use super::{acl::LDAPACL, attributes::LDAPAttributes, config::layout::EntityKind, datatypes::CIString, dn::LDAPDN, schema, traits::Mergeable};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LDAPEntry {
     pub dn: LDAPDN,
     pub attributes: LDAPAttributes,
     pub acls: LDAPACL,
+    /// Kind of the configured entity this entry comes from, set for the entities which may bind
+    pub kind: Option<EntityKind>,
 }
 impl LDAPEntry {
 
@@ -13,6 +15,7 @@ impl LDAPEntry {
             acls: Default::default(),
             dn,
             attributes,
+            kind: None,
         }
     }
 
@@ -28,39 +31,95 @@ impl LDAPEntry {
                 .all(|filter| self.matches_filter(filter)),
             ldap3_proto::LdapFilter::Or(ldap_filters) => ldap_filters.iter()
                 .any(|filter| self.matches_filter(filter)),
-            ldap3_proto::LdapFilter::Not(ldap_filter) => !self.matches_filter(&ldap_filter),
-            ldap3_proto::LdapFilter::Equality(attribute_name, attribute_value) => if let Some(attribute) = self.attributes.get_attribute(&CIString::new(attribute_name)) {
-                attribute.values.iter()
-                    .any(|value| value == attribute_value.as_bytes())
-            } else {
-                false
+            ldap3_proto::LdapFilter::Not(ldap_filter) => !self.matches_filter(ldap_filter),
+            ldap3_proto::LdapFilter::Equality(attribute_name, attribute_value) => {
+                let attribute_name = CIString::new(attribute_name);
+                if let Some(attribute) = self.attributes.get_attribute(&attribute_name) {
+                    let attribute_type = schema::attribute_type(&attribute_name);
+                    attribute.values.iter()
+                        .any(|value| attribute_type.matches(value, attribute_value.as_bytes()))
+                } else {
+                    false
+                }
             },
             ldap3_proto::LdapFilter::Present(attribute) => self.attributes.has_attribute(&CIString::new(attribute)),
-            ldap3_proto::LdapFilter::Substring(attribute_name, substring_filter) => if let Some(attribute) = self.attributes.get_attribute(&CIString::new(attribute_name)) {
-                // assemble a regex
-                let mut re = vec![];
-                if let Some(starts_with) = &substring_filter.initial {
-                    re.push(format!("^{starts_with}"));
-                }
-                for intermediate in &substring_filter.any {
-                    re.push(format!(".*{}.*", regex::escape(&intermediate)));
+            ldap3_proto::LdapFilter::Substring(attribute_name, substring_filter) => {
+                let attribute_name = CIString::new(attribute_name);
+                let Some(substring_rule) = schema::attribute_type(&attribute_name).substring else {
+                    return false;
+                };
+                if let Some(attribute) = self.attributes.get_attribute(&attribute_name) {
+                    attribute.values.iter()
+                        .any(|value| substring_rule.matches(value, substring_filter))
+                } else {
+                    false
                 }
-                if let Some(ends_with) = &substring_filter.final_ {
-                    re.push(format!("{ends_with}$"));
-                }
-                let re = re.join("");
-                let re = regex::Regex::new(&re).unwrap();
-
-                // if any matches the regex
+            },
+            ldap3_proto::LdapFilter::GreaterOrEqual(attribute_name, attribute_value) => self.matches_ordering(attribute_name, attribute_value, |ordering| ordering.is_ge()),
+            ldap3_proto::LdapFilter::LessOrEqual(attribute_name, attribute_value) => self.matches_ordering(attribute_name, attribute_value, |ordering| ordering.is_le()),
+            ldap3_proto::LdapFilter::Approx(attribute_name, attribute_value) => if let Some(attribute) = self.attributes.get_attribute(&CIString::new(attribute_name)) {
                 attribute.values.iter()
-                    .any(|value| re.is_match(&String::from_utf8_lossy(&value)))
+                    .any(|value| schema::approx_matches(value, attribute_value.as_bytes()))
             } else {
                 false
             },
-            f => {
-                tracing::warn!(filter = ?f, "Unsupported filter");
-                false
+            ldap3_proto::LdapFilter::Extensible(assertion) => self.matches_extensible(assertion),
+        }
+    }
+
+    /// Evaluate an extensible match filter (RFC 4511 section 4.5.1.7.7)
+    pub fn matches_extensible(&self, assertion: &ldap3_proto::proto::LdapMatchingRuleAssertion) -> bool {
+        // grab the matching rule
+        let matching_rule = match assertion.matching_rule.as_ref() {
+            Some(name) => match schema::MatchingRule::from_name_or_oid(name) {
+                Some(matching_rule) => Some(matching_rule),
+                None => {
+                    tracing::warn!(matching_rule = name, "Unsupported matching rule");
+                    return false;
+                },
             },
+            None => None,
+        };
+        let attribute_name = assertion.type_.as_ref().map(CIString::new);
+        if matching_rule.is_none() && attribute_name.is_none() {
+            return false;
+        }
+
+        // without an explicit rule the equality of the attribute is used
+        let is_selected = |name: &CIString| attribute_name.as_ref().is_none_or(|attribute_name| attribute_name == name);
+        let matches = |name: &CIString, value: &[u8]| matching_rule
+            .unwrap_or_else(|| schema::attribute_type(name).equality)
+            .matches(value, assertion.match_value.as_bytes());
+
+        // match attribute values
+        let attributes_match = self.attributes.iter()
+            .map(|attribute| (CIString::new(&attribute.name), attribute))
+            .filter(|(name, _)| is_selected(name))
+            .any(|(name, attribute)| attribute.values.iter().any(|value| matches(&name, value)));
+
+        // match DN components
+        attributes_match || (
+            assertion.dn_attributes
+                &&
+            self.dn.iter()
+                .map(|(k, v)| (CIString::new(k), v))
+                .filter(|(name, _)| is_selected(name))
+                .any(|(name, v)| matches(&name, v.as_bytes()))
+        )
+    }
+
+    /// Evaluate an ordering filter using the ordering rule of the attribute
+    fn matches_ordering(&self, attribute_name: &str, attribute_value: &str, accept: impl Fn(std::cmp::Ordering) -> bool) -> bool {
+        let attribute_name = CIString::new(attribute_name);
+        let Some(ordering_rule) = schema::attribute_type(&attribute_name).ordering else {
+            return false;
+        };
+        if let Some(attribute) = self.attributes.get_attribute(&attribute_name) {
+            attribute.values.iter()
+                .filter_map(|value| ordering_rule.compare(value, attribute_value.as_bytes()))
+                .any(accept)
+        } else {
+            false
         }
     }
 
@@ -81,5 +140,6 @@ impl Mergeable<Self> for LDAPEntry {
         assert_eq!(self.dn, other.dn);
         self.attributes.merge(other.attributes);
         self.acls.merge(other.acls);
+        self.kind.merge(other.kind);
     }
 }
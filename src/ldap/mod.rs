@@ -2,9 +2,13 @@ pub mod acl;
 pub mod attribute;
 pub mod attributes;
 pub mod config;
+pub mod connection;
 pub mod database;
 pub mod datatypes;
+pub mod diff;
 pub mod dn;
 pub mod entry;
+pub mod password;
+pub mod schema;
 pub mod server;
 pub mod traits;
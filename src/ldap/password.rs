@@ -0,0 +1,265 @@
+use argon2::{PasswordHasher, PasswordVerifier};
+use base64::Engine;
+use subtle::ConstantTimeEq;
+
+/// Schemes of the `{SCHEME}` prefixed passwords (RFC 2307 section 5.3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordScheme {
+    Cleartext,
+    Ssha,
+    Ssha512,
+    Argon2,
+    Bcrypt,
+    Crypt,
+    Pbkdf2,
+    Pbkdf2Sha256,
+    Pbkdf2Sha512,
+}
+impl PasswordScheme {
+
+    /// Schemes along with their prefix
+    const SCHEMES: &[(Self, &'static str)] = &[
+        (Self::Cleartext, "CLEARTEXT"),
+        (Self::Ssha, "SSHA"),
+        (Self::Ssha512, "SSHA512"),
+        (Self::Argon2, "ARGON2"),
+        (Self::Bcrypt, "BCRYPT"),
+        (Self::Crypt, "CRYPT"),
+        (Self::Pbkdf2, "PBKDF2"),
+        (Self::Pbkdf2, "PBKDF2-SHA1"),
+        (Self::Pbkdf2Sha256, "PBKDF2-SHA256"),
+        (Self::Pbkdf2Sha512, "PBKDF2-SHA512"),
+    ];
+
+    /// Scheme used for passwords set by clients
+    pub const DEFAULT: Self = Self::Argon2;
+
+    pub fn name(self) -> &'static str {
+        Self::SCHEMES.iter()
+            .find(|(scheme, _)| *scheme == self)
+            .map(|(_, name)| *name)
+            .expect("Scheme without a name")
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::SCHEMES.iter()
+            .find(|(_, scheme_name)| scheme_name.eq_ignore_ascii_case(name))
+            .map(|(scheme, _)| *scheme)
+    }
+
+    /// The supported schemes, without aliases
+    pub fn all() -> impl Iterator<Item = Self> {
+        Self::SCHEMES.iter()
+            .enumerate()
+            .filter(|(i, (scheme, _))| !Self::SCHEMES[..*i].iter().any(|(other, _)| other == scheme))
+            .map(|(_, (scheme, _))| *scheme)
+    }
+
+    /// Split a stored password into its scheme and its value, unprefixed passwords are cleartext
+    pub fn parse(stored: &str) -> Option<(Self, &str)> {
+        if let Some(rest) = stored.strip_prefix('{')
+            && let Some((name, value)) = rest.split_once('}')
+        {
+            Self::from_name(name).map(|scheme| (scheme, value))
+        } else {
+            Some((Self::Cleartext, stored))
+        }
+    }
+
+    /// Hash a password, prefixing it with the scheme
+    pub fn hash(self, password: &str) -> anyhow::Result<String> {
+        self.hash_with_cost(password, None)
+    }
+
+    /// Hash a password with a custom cost (bcrypt cost, Argon2 iterations, crypt and PBKDF2 rounds)
+    pub fn hash_with_cost(self, password: &str, cost: Option<u32>) -> anyhow::Result<String> {
+        let value = match self {
+            Self::Cleartext | Self::Ssha | Self::Ssha512 if cost.is_some() => {
+                return Err(anyhow::anyhow!("The {} scheme has no cost", self.name()));
+            },
+            Self::Cleartext => return Ok(password.to_string()),
+            Self::Ssha => salted_digest::<sha1::Sha1>(password, &rand::random::<[u8; 8]>()),
+            Self::Ssha512 => salted_digest::<sha2::Sha512>(password, &rand::random::<[u8; 8]>()),
+            Self::Argon2 => {
+                let salt = argon2::password_hash::SaltString::encode_b64(&rand::random::<[u8; 16]>())
+                    .map_err(|err| anyhow::anyhow!("Invalid salt: {err}"))?;
+                let params = argon2::Params::new(
+                    argon2::Params::DEFAULT_M_COST,
+                    cost.unwrap_or(argon2::Params::DEFAULT_T_COST),
+                    argon2::Params::DEFAULT_P_COST,
+                    None,
+                ).map_err(|err| anyhow::anyhow!("Invalid Argon2 parameters: {err}"))?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password(password.as_bytes(), &salt)
+                    .map_err(|err| anyhow::anyhow!("Failed hashing password: {err}"))?
+                    .to_string()
+            },
+            Self::Bcrypt => bcrypt::hash_with_salt(password, cost.unwrap_or(bcrypt::DEFAULT_COST), rand::random())?.to_string(),
+            Self::Crypt => pwhash::sha512_crypt::hash_with(pwhash::HashSetup { salt: None, rounds: cost }, password)?,
+            Self::Pbkdf2 => pbkdf2_digest(PBKDF2_SHA1, password, &rand::random::<[u8; 16]>(), cost.unwrap_or(131000)),
+            Self::Pbkdf2Sha256 => pbkdf2_digest(PBKDF2_SHA256, password, &rand::random::<[u8; 16]>(), cost.unwrap_or(29000)),
+            Self::Pbkdf2Sha512 => pbkdf2_digest(PBKDF2_SHA512, password, &rand::random::<[u8; 16]>(), cost.unwrap_or(25000)),
+        };
+        Ok(format!("{{{}}}{}", self.name(), value))
+    }
+
+    /// Check a password against a value of this scheme
+    fn verify(self, value: &str, password: &str) -> bool {
+        match self {
+            Self::Cleartext => value.as_bytes().ct_eq(password.as_bytes()).into(),
+            Self::Ssha => verify_salted_digest::<sha1::Sha1>(value, password),
+            Self::Ssha512 => verify_salted_digest::<sha2::Sha512>(value, password),
+            Self::Argon2 => argon2::PasswordHash::new(value)
+                .is_ok_and(|hash| argon2::Argon2::default().verify_password(password.as_bytes(), &hash).is_ok()),
+            Self::Bcrypt => bcrypt::verify(password, value).unwrap_or(false),
+            Self::Crypt => pwhash::unix::verify(password, value),
+            Self::Pbkdf2 => verify_pbkdf2_digest(PBKDF2_SHA1, value, password),
+            Self::Pbkdf2Sha256 => verify_pbkdf2_digest(PBKDF2_SHA256, value, password),
+            Self::Pbkdf2Sha512 => verify_pbkdf2_digest(PBKDF2_SHA512, value, password),
+        }
+    }
+
+}
+
+impl std::str::FromStr for PasswordScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s.trim_start_matches('{').trim_end_matches('}'))
+            .ok_or_else(|| format!("unknown scheme, expected one of {}", Self::all().map(Self::name).collect::<Vec<_>>().join(", ")))
+    }
+}
+
+/// Check a password against a stored one, passwords of unknown schemes never match
+pub fn verify_password(stored: &str, password: &str) -> bool {
+    match PasswordScheme::parse(stored) {
+        Some((scheme, value)) => scheme.verify(value, password),
+        None => {
+            tracing::warn!("Unsupported password scheme");
+            false
+        },
+    }
+}
+
+/// Spend the time of verifying a password of the default scheme, for binds to entries without one
+pub fn verify_dummy_password(password: &str) {
+    static DUMMY: std::sync::LazyLock<String> = std::sync::LazyLock::new(|| {
+        let password = base64::engine::general_purpose::STANDARD.encode(rand::random::<[u8; 16]>());
+        PasswordScheme::DEFAULT.hash(&password).expect("Failed hashing the dummy password")
+    });
+    verify_password(&DUMMY, password);
+}
+
+/// Whether a stored password is in cleartext
+pub fn is_cleartext(stored: &str) -> bool {
+    matches!(PasswordScheme::parse(stored), Some((PasswordScheme::Cleartext, _)))
+}
+
+//
+// Salted digests, base64(digest(password + salt) + salt)
+//
+
+fn salted_digest<D: sha2::Digest>(password: &str, salt: &[u8]) -> String {
+    let mut value = D::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .finalize()
+        .to_vec();
+    value.extend_from_slice(salt);
+    base64::engine::general_purpose::STANDARD.encode(value)
+}
+
+fn verify_salted_digest<D: sha2::Digest>(value: &str, password: &str) -> bool {
+    let Ok(value) = base64::engine::general_purpose::STANDARD.decode(value) else {
+        return false;
+    };
+    if value.len() <= <D as sha2::Digest>::output_size() {
+        return false;
+    }
+    let (digest, salt) = value.split_at(<D as sha2::Digest>::output_size());
+    D::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .finalize()
+        .as_slice()
+        .ct_eq(digest)
+        .into()
+}
+
+//
+// PBKDF2 digests, rounds$salt$digest in adapted base64 (as OpenLDAP and passlib)
+//
+
+fn ab64_encode(value: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD_NO_PAD.encode(value).replace('+', ".")
+}
+
+fn ab64_decode(value: &str) -> Option<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD_NO_PAD.decode(value.trim_end_matches('=').replace('.', "+")).ok()
+}
+
+/// A PBKDF2 implementation along with its digest length
+type Pbkdf2Function = (fn(&[u8], &[u8], u32, &mut [u8]), usize);
+
+const PBKDF2_SHA1: Pbkdf2Function = (pbkdf2::pbkdf2_hmac::<sha1::Sha1>, 20);
+const PBKDF2_SHA256: Pbkdf2Function = (pbkdf2::pbkdf2_hmac::<sha2::Sha256>, 32);
+const PBKDF2_SHA512: Pbkdf2Function = (pbkdf2::pbkdf2_hmac::<sha2::Sha512>, 64);
+
+fn pbkdf2_digest((pbkdf2, length): Pbkdf2Function, password: &str, salt: &[u8], rounds: u32) -> String {
+    let mut digest = vec![0; length];
+    pbkdf2(password.as_bytes(), salt, rounds, &mut digest);
+    format!("{rounds}${}${}", ab64_encode(salt), ab64_encode(&digest))
+}
+
+fn verify_pbkdf2_digest((pbkdf2, _): Pbkdf2Function, value: &str, password: &str) -> bool {
+    let mut parts = value.splitn(3, '$');
+    let (Some(rounds), Some(salt), Some(digest)) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    let (Ok(rounds), Some(salt), Some(digest)) = (rounds.parse::<u32>(), ab64_decode(salt), ab64_decode(digest)) else {
+        return false;
+    };
+    if rounds == 0 || digest.is_empty() {
+        return false;
+    }
+    let mut computed = vec![0; digest.len()];
+    pbkdf2(password.as_bytes(), &salt, rounds, &mut computed);
+    computed.ct_eq(&digest).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_cleartext, verify_password, PasswordScheme};
+
+    #[test]
+    fn test_password_schemes() {
+        for scheme in PasswordScheme::all() {
+            assert_eq!(PasswordScheme::from_name(&scheme.name().to_lowercase()), Some(scheme));
+            let stored = scheme.hash("secret").unwrap();
+            assert!(verify_password(&stored, "secret"), "{stored}");
+            assert!(!verify_password(&stored, "wrong"), "{stored}");
+            assert_eq!(is_cleartext(&stored), scheme == PasswordScheme::Cleartext);
+        }
+
+        // costs are tunable
+        assert!(verify_password(&PasswordScheme::Bcrypt.hash_with_cost("secret", Some(4)).unwrap(), "secret"));
+        assert!(PasswordScheme::Pbkdf2Sha256.hash_with_cost("secret", Some(1000)).unwrap().starts_with("{PBKDF2-SHA256}1000$"));
+        assert!(PasswordScheme::Ssha.hash_with_cost("secret", Some(1000)).is_err());
+    }
+
+    #[test]
+    fn test_known_passwords() {
+        // cleartext
+        assert!(verify_password("secret", "secret"));
+        assert!(verify_password("{CLEARTEXT}{secret}", "{secret}"));
+        // base64(sha1("secret" + "salt") + "salt")
+        assert!(verify_password("{SSHA}gVK8WC9YyFT1gMsQHTGCgT3sSv5zYWx0", "secret"));
+        // pbkdf2_hmac("sha256", "secret", "salt", 1000)
+        assert!(verify_password("{PBKDF2-SHA256}1000$c2FsdA$qN.JnzxPIE2WfgrWPAkph8EAVeuwF7PZ0ordIY1Peq0", "secret"));
+        // openssl passwd -6 -salt saltsalt secret
+        assert!(verify_password("{CRYPT}$6$saltsalt$TVLlQcbpFVof5W3Yz4DTP6gRstiNuHwwTt6GLc1E5n0U0aDehy0S5knV8wiOQSpT0Y77vwPZN.Pq.H91p5hVO1", "secret"));
+        // unknown schemes never match
+        assert!(!verify_password("{MD5}secret", "{MD5}secret"));
+    }
+
+}
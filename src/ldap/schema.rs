@@ -0,0 +1,611 @@
+use std::cmp::Ordering;
+
+use super::{datatypes::CIString, dn::LDAPDN};
+
+/// Ordering matching rules (RFC 4517 section 4.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderingRule {
+    /// caseIgnoreOrderingMatch
+    CaseIgnore,
+    /// caseExactOrderingMatch
+    CaseExact,
+    /// integerOrderingMatch
+    Integer,
+    /// generalizedTimeOrderingMatch
+    GeneralizedTime,
+}
+impl OrderingRule {
+
+    /// Compare an attribute value with an assertion value, `None` if either is not valid for the rule
+    pub fn compare(&self, value: &[u8], assertion: &[u8]) -> Option<Ordering> {
+        match self {
+            Self::CaseIgnore => {
+                let value = std::str::from_utf8(value).ok()?;
+                let assertion = std::str::from_utf8(assertion).ok()?;
+                Some(prepare_string(value, true).cmp(&prepare_string(assertion, true)))
+            },
+            Self::CaseExact => {
+                let value = std::str::from_utf8(value).ok()?;
+                let assertion = std::str::from_utf8(assertion).ok()?;
+                Some(prepare_string(value, false).cmp(&prepare_string(assertion, false)))
+            },
+            Self::Integer => {
+                let value = parse_integer(value)?;
+                let assertion = parse_integer(assertion)?;
+                Some(value.cmp(&assertion))
+            },
+            Self::GeneralizedTime => {
+                let value = parse_generalized_time(value)?;
+                let assertion = parse_generalized_time(assertion)?;
+                Some(value.cmp(&assertion))
+            },
+        }
+    }
+
+}
+
+/// Equality matching rules (RFC 4517 section 4.2), plus the bitwise rules usable in extensible match filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchingRule {
+    /// caseIgnoreMatch
+    CaseIgnore,
+    /// caseIgnoreIA5Match
+    CaseIgnoreIA5,
+    /// caseExactMatch
+    CaseExact,
+    /// caseExactIA5Match
+    CaseExactIA5,
+    /// distinguishedNameMatch
+    DistinguishedName,
+    /// generalizedTimeMatch
+    GeneralizedTime,
+    /// integerMatch
+    Integer,
+    /// objectIdentifierMatch
+    ObjectIdentifier,
+    /// octetStringMatch
+    OctetString,
+    /// telephoneNumberMatch
+    TelephoneNumber,
+    /// uniqueMemberMatch
+    UniqueMember,
+    /// UUIDMatch (RFC 4530)
+    Uuid,
+    /// LDAP_MATCHING_RULE_BIT_AND, matches if all bits of the assertion are set
+    IntegerBitAnd,
+    /// LDAP_MATCHING_RULE_BIT_OR, matches if any bit of the assertion is set
+    IntegerBitOr,
+    /// LDAP_MATCHING_RULE_IN_CHAIN, matches if the assertion DN is reached following the values of the attribute,
+    /// a single step is taken on its own and the database follows the chain
+    InChain,
+}
+impl MatchingRule {
+
+    const RULES: &[(Self, &'static str, &'static str)] = &[
+        (Self::CaseIgnore, "caseIgnoreMatch", "2.5.13.2"),
+        (Self::CaseIgnoreIA5, "caseIgnoreIA5Match", "1.3.6.1.4.1.1466.109.114.2"),
+        (Self::CaseExact, "caseExactMatch", "2.5.13.5"),
+        (Self::CaseExactIA5, "caseExactIA5Match", "1.3.6.1.4.1.1466.109.114.1"),
+        (Self::DistinguishedName, "distinguishedNameMatch", "2.5.13.1"),
+        (Self::GeneralizedTime, "generalizedTimeMatch", "2.5.13.27"),
+        (Self::Integer, "integerMatch", "2.5.13.14"),
+        (Self::ObjectIdentifier, "objectIdentifierMatch", "2.5.13.0"),
+        (Self::OctetString, "octetStringMatch", "2.5.13.17"),
+        (Self::TelephoneNumber, "telephoneNumberMatch", "2.5.13.20"),
+        (Self::UniqueMember, "uniqueMemberMatch", "2.5.13.23"),
+        (Self::Uuid, "UUIDMatch", "1.3.6.1.1.16.2"),
+        (Self::IntegerBitAnd, "integerBitAndMatch", "1.2.840.113556.1.4.803"),
+        (Self::IntegerBitOr, "integerBitOrMatch", "1.2.840.113556.1.4.804"),
+        (Self::InChain, "inChainMatch", "1.2.840.113556.1.4.1941"),
+    ];
+
+    /// Look up a matching rule by its name or OID
+    pub fn from_name_or_oid(s: &str) -> Option<Self> {
+        Self::RULES.iter()
+            .find(|(_, name, oid)| name.eq_ignore_ascii_case(s) || *oid == s)
+            .map(|(rule, _, _)| *rule)
+    }
+
+    /// Normalize a value so that values matching under this rule are equal byte-wise,
+    /// `None` if the value is not valid for the rule or the rule is not an equality rule
+    pub fn normalize(&self, value: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Self::CaseIgnore | Self::CaseIgnoreIA5 | Self::ObjectIdentifier => {
+                Some(prepare_string(std::str::from_utf8(value).ok()?, true).into_bytes())
+            },
+            Self::CaseExact | Self::CaseExactIA5 => {
+                Some(prepare_string(std::str::from_utf8(value).ok()?, false).into_bytes())
+            },
+            Self::DistinguishedName | Self::UniqueMember | Self::InChain => {
+                let dn = std::str::from_utf8(value).ok()?.parse::<LDAPDN>().ok()?;
+                Some(normalize_dn(&dn).to_string().into_bytes())
+            },
+            Self::GeneralizedTime => Some(parse_generalized_time(value)?.to_string().into_bytes()),
+            Self::Integer => Some(parse_integer(value)?.to_string().into_bytes()),
+            Self::OctetString => Some(value.to_vec()),
+            Self::TelephoneNumber => Some(normalize_telephone_number(std::str::from_utf8(value).ok()?).into_bytes()),
+            Self::Uuid => Some(uuid::Uuid::try_parse_ascii(value).ok()?.as_hyphenated().to_string().into_bytes()),
+            Self::IntegerBitAnd | Self::IntegerBitOr => None,
+        }
+    }
+
+    /// Check whether an attribute value matches an assertion value
+    pub fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+        match self {
+            Self::IntegerBitAnd => match (parse_integer(value), parse_integer(assertion)) {
+                (Some(value), Some(assertion)) => value & assertion == assertion,
+                _ => false,
+            },
+            Self::IntegerBitOr => match (parse_integer(value), parse_integer(assertion)) {
+                (Some(value), Some(assertion)) => value & assertion != 0,
+                _ => false,
+            },
+            _ => match (self.normalize(value), self.normalize(assertion)) {
+                (Some(value), Some(assertion)) => value == assertion,
+                _ => false,
+            },
+        }
+    }
+
+}
+
+/// Substring matching rules (RFC 4517 section 4.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubstringRule {
+    /// caseIgnoreSubstringsMatch
+    CaseIgnore,
+    /// caseIgnoreIA5SubstringsMatch
+    CaseIgnoreIA5,
+    /// caseExactSubstringsMatch
+    CaseExact,
+    /// telephoneNumberSubstringsMatch
+    TelephoneNumber,
+}
+impl SubstringRule {
+
+    /// Normalize a value or a substring assertion component, `None` if the value is not valid for the rule
+    pub fn normalize(&self, value: &[u8]) -> Option<String> {
+        let value = std::str::from_utf8(value).ok()?;
+        match self {
+            Self::CaseIgnore | Self::CaseIgnoreIA5 => Some(prepare_string(value, true)),
+            Self::CaseExact => Some(prepare_string(value, false)),
+            Self::TelephoneNumber => Some(normalize_telephone_number(value)),
+        }
+    }
+
+    /// Ordered substring matching (RFC 4511 section 4.5.1.7.2)
+    ///
+    /// The initial component anchors to the start of the value, the final one to its end,
+    /// and every other component has to appear in order without overlapping.
+    pub fn matches(&self, value: &[u8], filter: &ldap3_proto::proto::LdapSubstringFilter) -> bool {
+        let Some(value) = self.normalize(value) else {
+            return false;
+        };
+        let normalize = |s: &String| self.normalize(s.as_bytes());
+        let mut rest = value.as_str();
+
+        // initial
+        if let Some(initial) = filter.initial.as_ref() {
+            let Some(initial) = normalize(initial) else { return false };
+            match rest.strip_prefix(initial.as_str()) {
+                Some(stripped) => rest = stripped,
+                None => return false,
+            }
+        }
+
+        // final
+        if let Some(final_) = filter.final_.as_ref() {
+            let Some(final_) = normalize(final_) else { return false };
+            match rest.strip_suffix(final_.as_str()) {
+                Some(stripped) => rest = stripped,
+                None => return false,
+            }
+        }
+
+        // any
+        for any in filter.any.iter() {
+            let Some(any) = normalize(any) else { return false };
+            match rest.find(any.as_str()) {
+                Some(position) => rest = &rest[position + any.len()..],
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+}
+
+/// Description of how the values of an attribute are compared
+#[derive(Debug)]
+pub struct AttributeType {
+    pub names: &'static [&'static str],
+    pub equality: MatchingRule,
+    pub ordering: Option<OrderingRule>,
+    pub substring: Option<SubstringRule>,
+    /// Operational attributes are only returned when explicitly requested (RFC 4512 section 3.4)
+    pub operational: bool,
+}
+impl AttributeType {
+
+    const fn new(names: &'static [&'static str], equality: MatchingRule, ordering: Option<OrderingRule>, substring: Option<SubstringRule>) -> Self {
+        Self { names, equality, ordering, substring, operational: false }
+    }
+
+    const fn operational(mut self) -> Self {
+        self.operational = true;
+        self
+    }
+
+    /// Whether a name refers to this attribute type
+    pub fn has_name(&self, name: &CIString) -> bool {
+        self.names.iter().any(|x| &CIString::new(x) == name)
+    }
+
+    /// Normalize a value using the equality rule of the attribute
+    pub fn normalize(&self, value: &[u8]) -> Option<Vec<u8>> {
+        self.equality.normalize(value)
+    }
+
+    /// Check whether an attribute value equals an assertion value
+    pub fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+        self.equality.matches(value, assertion)
+    }
+
+}
+
+/// Attribute type used for attributes not described in the schema (e.g. extra attributes)
+const DEFAULT_ATTRIBUTE_TYPE: AttributeType = AttributeType::new(&[], MatchingRule::CaseIgnore, Some(OrderingRule::CaseIgnore), Some(SubstringRule::CaseIgnore));
+
+/// The attribute types femtoLDAP knows about
+const ATTRIBUTE_TYPES: &[AttributeType] = {
+    use MatchingRule as M;
+    use OrderingRule as O;
+    use SubstringRule as S;
+    &[
+        // RFC 4512
+        AttributeType::new(&["objectClass"], M::ObjectIdentifier, None, None),
+        // RFC 4519
+        AttributeType::new(&["cn", "commonName"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["dc", "domainComponent"], M::CaseIgnoreIA5, Some(O::CaseIgnore), Some(S::CaseIgnoreIA5)),
+        AttributeType::new(&["description"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["givenName"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["initials"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["member"], M::DistinguishedName, None, None),
+        AttributeType::new(&["ou", "organizationalUnitName"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["sn", "surname"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["telephoneNumber"], M::TelephoneNumber, None, Some(S::TelephoneNumber)),
+        AttributeType::new(&["uid", "userid"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["uniqueMember"], M::UniqueMember, None, None),
+        AttributeType::new(&["userPassword"], M::OctetString, None, None),
+        // RFC 4524
+        AttributeType::new(&["mail", "rfc822Mailbox"], M::CaseIgnoreIA5, Some(O::CaseIgnore), Some(S::CaseIgnoreIA5)),
+        AttributeType::new(&["mobile", "mobileTelephoneNumber"], M::TelephoneNumber, None, Some(S::TelephoneNumber)),
+        // RFC 2798
+        AttributeType::new(&["displayName"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        AttributeType::new(&["preferredLanguage"], M::CaseIgnore, Some(O::CaseIgnore), Some(S::CaseIgnore)),
+        // RFC 2307
+        AttributeType::new(&["uidNumber"], M::Integer, Some(O::Integer), None),
+        AttributeType::new(&["gidNumber"], M::Integer, Some(O::Integer), None),
+        AttributeType::new(&["homeDirectory"], M::CaseExactIA5, Some(O::CaseExact), Some(S::CaseExact)),
+        AttributeType::new(&["loginShell"], M::CaseExactIA5, Some(O::CaseExact), Some(S::CaseExact)),
+        AttributeType::new(&["memberUid"], M::CaseExactIA5, Some(O::CaseExact), Some(S::CaseExact)),
+        AttributeType::new(&["rfc822mailMember"], M::CaseIgnoreIA5, Some(O::CaseIgnore), Some(S::CaseIgnoreIA5)),
+        // RFC 4530
+        AttributeType::new(&["entryUUID"], M::Uuid, Some(O::CaseIgnore), None).operational(),
+        // RFC 5020
+        AttributeType::new(&["entryDN"], M::DistinguishedName, None, None).operational(),
+        // RFC 4512 operational attributes
+        AttributeType::new(&["createTimestamp"], M::GeneralizedTime, Some(O::GeneralizedTime), None).operational(),
+        AttributeType::new(&["modifyTimestamp"], M::GeneralizedTime, Some(O::GeneralizedTime), None).operational(),
+        AttributeType::new(&["subschemaSubentry"], M::DistinguishedName, None, None).operational(),
+        // RFC 4512 root DSE attributes
+        AttributeType::new(&["altServer"], M::CaseIgnoreIA5, None, None).operational(),
+        AttributeType::new(&["namingContexts"], M::DistinguishedName, None, None).operational(),
+        AttributeType::new(&["supportedControl"], M::ObjectIdentifier, None, None).operational(),
+        AttributeType::new(&["supportedExtension"], M::ObjectIdentifier, None, None).operational(),
+        AttributeType::new(&["supportedFeatures"], M::ObjectIdentifier, None, None).operational(),
+        AttributeType::new(&["supportedLDAPVersion"], M::Integer, None, None).operational(),
+        AttributeType::new(&["supportedSASLMechanisms"], M::CaseIgnore, None, None).operational(),
+        // RFC 3112
+        AttributeType::new(&["supportedAuthPasswordSchemes"], M::CaseExactIA5, None, None).operational(),
+        // RFC 3045
+        AttributeType::new(&["vendorName"], M::CaseExactIA5, None, None).operational(),
+        AttributeType::new(&["vendorVersion"], M::CaseExactIA5, None, None).operational(),
+        // femtoLDAP root DSE attributes
+        AttributeType::new(&["femtoldapReloadGeneration"], M::Integer, Some(O::Integer), None).operational(),
+        AttributeType::new(&["femtoldapReloadTimestamp"], M::GeneralizedTime, Some(O::GeneralizedTime), None).operational(),
+        // common non-standard attributes
+        AttributeType::new(&["mailAlias"], M::CaseIgnoreIA5, Some(O::CaseIgnore), Some(S::CaseIgnoreIA5)),
+        AttributeType::new(&["memberOf"], M::DistinguishedName, None, None),
+        AttributeType::new(&["sshPublicKey"], M::OctetString, Some(O::CaseExact), Some(S::CaseExact)),
+    ]
+};
+
+/// Get the schema description of an attribute
+pub fn attribute_type(name: &CIString) -> &'static AttributeType {
+    ATTRIBUTE_TYPES.iter()
+        .find(|attribute_type| attribute_type.has_name(name))
+        .unwrap_or(&DEFAULT_ATTRIBUTE_TYPE)
+}
+
+/// Normalize a DN as distinguishedNameMatch does, so that equal DNs compare equal
+pub fn normalize_dn(dn: &LDAPDN) -> LDAPDN {
+    dn.iter()
+        .map(|(k, v)| (k.trim().to_lowercase(), prepare_string(v, true)))
+        .collect()
+}
+
+/// Approximate matching (`~=`)
+///
+/// Values are split into words, and match when they have the same number of words
+/// and every word has the same American Soundex code as its counterpart.
+/// Words without letters (e.g. numbers) have to match exactly, ignoring case.
+pub fn approx_matches(value: &[u8], assertion: &[u8]) -> bool {
+    let (Ok(value), Ok(assertion)) = (std::str::from_utf8(value), std::str::from_utf8(assertion)) else {
+        return false;
+    };
+    let value = value.split_whitespace().collect::<Vec<_>>();
+    let assertion = assertion.split_whitespace().collect::<Vec<_>>();
+    value.len() == assertion.len()
+        &&
+    value.iter()
+        .zip(assertion.iter())
+        .all(|(a, b)| match (soundex(a), soundex(b)) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => a.to_lowercase() == b.to_lowercase(),
+            _ => false,
+        })
+}
+
+/// American Soundex code of a word, `None` if the word has no ASCII letters
+fn soundex(word: &str) -> Option<String> {
+    const fn code(c: char) -> Option<char> {
+        match c {
+            'b' | 'f' | 'p' | 'v' => Some('1'),
+            'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+            'd' | 't' => Some('3'),
+            'l' => Some('4'),
+            'm' | 'n' => Some('5'),
+            'r' => Some('6'),
+            _ => None,
+        }
+    }
+    let mut letters = word.chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase());
+    let first = letters.next()?;
+    let mut result = String::from(first.to_ascii_uppercase());
+    let mut last_code = code(first);
+    for c in letters {
+        let current_code = code(c);
+        if let Some(digit) = current_code && current_code != last_code {
+            result.push(digit);
+            if result.len() == 4 {
+                break;
+            }
+        }
+        // 'h' and 'w' do not separate letters with the same code
+        if c != 'h' && c != 'w' {
+            last_code = current_code;
+        }
+    }
+    while result.len() < 4 {
+        result.push('0');
+    }
+    Some(result)
+}
+
+/// Insignificant space handling and optional case folding (RFC 4518 section 2.6.1)
+fn prepare_string(s: &str, case_ignore: bool) -> String {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    if case_ignore {
+        s.to_lowercase()
+    } else {
+        s
+    }
+}
+
+/// Drop spaces and hyphens, and fold case (RFC 4518 section 2.6.2)
+fn normalize_telephone_number(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Parse an INTEGER value (RFC 4517 section 3.3.16)
+fn parse_integer(value: &[u8]) -> Option<i128> {
+    std::str::from_utf8(value).ok()?.trim().parse().ok()
+}
+
+/// Parse a GeneralizedTime value (RFC 4517 section 3.3.13) into nanoseconds since the epoch
+fn parse_generalized_time(value: &[u8]) -> Option<i128> {
+    let value = std::str::from_utf8(value).ok()?.trim();
+    let digits = |s: &str| -> Option<i128> {
+        if !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()) { s.parse().ok() } else { None }
+    };
+
+    // split off the time zone
+    let (value, offset_seconds) = if let Some(value) = value.strip_suffix('Z') {
+        (value, 0)
+    } else if let Some(position) = value.rfind(['+', '-']) {
+        let (value, offset) = value.split_at(position);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let offset = &offset[1..];
+        let (hours, minutes) = match offset.len() {
+            2 => (digits(offset)?, 0),
+            4 => (digits(&offset[..2])?, digits(&offset[2..])?),
+            _ => return None,
+        };
+        (value, sign * (hours * 3600 + minutes * 60))
+    } else {
+        return None;
+    };
+
+    // split off the fraction
+    let (value, fraction) = match value.split_once(['.', ',']) {
+        Some((value, fraction)) => (value, Some(fraction)),
+        None => (value, None),
+    };
+    if value.len() < 10 || value.len() > 14 || value.len() % 2 != 0 {
+        return None;
+    }
+    let year = digits(&value[0..4])?;
+    let month = digits(&value[4..6])?;
+    let day = digits(&value[6..8])?;
+    let hour = digits(&value[8..10])?;
+    let minute = if value.len() >= 12 { digits(&value[10..12])? } else { 0 };
+    let second = if value.len() >= 14 { digits(&value[12..14])? } else { 0 };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // the fraction applies to the last specified unit
+    let unit_nanoseconds: i128 = match value.len() {
+        10 => 3600 * 1_000_000_000,
+        12 => 60 * 1_000_000_000,
+        _ => 1_000_000_000,
+    };
+    let fraction_nanoseconds = match fraction {
+        Some(fraction) => {
+            digits(fraction)?;
+            let mut nanoseconds = 0;
+            let mut scale = unit_nanoseconds;
+            for digit in fraction.bytes().take(18) {
+                scale /= 10;
+                nanoseconds += i128::from(digit - b'0') * scale;
+            }
+            nanoseconds
+        },
+        None => 0,
+    };
+
+    // days since the epoch (proleptic Gregorian calendar)
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset_seconds;
+    Some(seconds * 1_000_000_000 + fraction_nanoseconds)
+}
+
+/// Format a point in time as a UTC generalized time, with a seconds precision
+pub fn format_generalized_time(time: std::time::SystemTime) -> String {
+    let seconds = match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+    let (days, seconds) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // civil date from days since the epoch (proleptic Gregorian calendar)
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let m = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * m + 2) / 5 + 1;
+    let month = if m < 10 { m + 3 } else { m - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}{month:02}{day:02}{:02}{:02}{:02}Z", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::ldap::datatypes::CIString;
+
+    use super::{approx_matches, attribute_type, format_generalized_time, parse_generalized_time, soundex, MatchingRule, OrderingRule, SubstringRule};
+
+    #[test]
+    fn test_ordering_rules() {
+        assert_eq!(OrderingRule::Integer.compare(b"1000", b"999"), Some(Ordering::Greater));
+        assert_eq!(OrderingRule::Integer.compare(b"-5", b"3"), Some(Ordering::Less));
+        assert_eq!(OrderingRule::Integer.compare(b"abc", b"3"), None);
+        assert_eq!(OrderingRule::CaseIgnore.compare(b"Alice", b"alice"), Some(Ordering::Equal));
+        assert_eq!(OrderingRule::CaseExact.compare(b"Alice", b"alice"), Some(Ordering::Less));
+        assert_eq!(OrderingRule::GeneralizedTime.compare(b"20250101120000Z", b"20250101130000+0200"), Some(Ordering::Greater));
+        assert_eq!(OrderingRule::GeneralizedTime.compare(b"2025010112Z", b"20250101120000.5Z"), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn test_matching_rules() {
+        assert_eq!(MatchingRule::from_name_or_oid("1.2.840.113556.1.4.803"), Some(MatchingRule::IntegerBitAnd));
+        assert_eq!(MatchingRule::from_name_or_oid("caseignorematch"), Some(MatchingRule::CaseIgnore));
+        assert_eq!(MatchingRule::from_name_or_oid("unknownMatch"), None);
+        assert!(MatchingRule::IntegerBitAnd.matches(b"514", b"2"));
+        assert!(!MatchingRule::IntegerBitAnd.matches(b"512", b"3"));
+        assert!(MatchingRule::IntegerBitOr.matches(b"512", b"513"));
+        assert!(!MatchingRule::IntegerBitOr.matches(b"512", b"1"));
+        assert!(MatchingRule::CaseIgnore.matches(b"Sample  User", b"sample user"));
+        assert!(!MatchingRule::CaseExact.matches(b"Sample User", b"sample user"));
+        assert!(MatchingRule::DistinguishedName.matches(b"uid=A,ou=users,dc=example,dc=com", b"UID=a,ou=Users,dc=example,dc=com"));
+        assert!(MatchingRule::TelephoneNumber.matches(b"+1 999 000", b"+1-999000"));
+        assert!(MatchingRule::Integer.matches(b"1000", b" 1000"));
+        assert!(!MatchingRule::Integer.matches(b"1000", b"abc"));
+        assert!(MatchingRule::Uuid.matches(b"6BA7B810-9DAD-11D1-80B4-00C04FD430C8", b"6ba7b810-9dad-11d1-80b4-00c04fd430c8"));
+    }
+
+    #[test]
+    fn test_substring_rules() {
+        let filter = |initial: Option<&str>, any: &[&str], final_: Option<&str>| ldap3_proto::proto::LdapSubstringFilter {
+            initial: initial.map(str::to_string),
+            any: any.iter().map(|x| x.to_string()).collect(),
+            final_: final_.map(str::to_string),
+        };
+        // special characters are literal
+        assert!(SubstringRule::CaseIgnore.matches(b"a.b", &filter(Some("a.b"), &[], None)));
+        assert!(!SubstringRule::CaseIgnore.matches(b"axb", &filter(Some("a.b"), &[], None)));
+        assert!(SubstringRule::CaseIgnoreIA5.matches(b"user+tag@x.com", &filter(None, &[], Some("+tag@X.com"))));
+        assert!(!SubstringRule::CaseIgnoreIA5.matches(b"user+tag@x.com.evil", &filter(None, &[], Some("+tag@x.com"))));
+        // components are ordered and do not overlap
+        assert!(SubstringRule::CaseExact.matches(b"abcabc", &filter(Some("ab"), &["ca"], Some("bc"))));
+        assert!(!SubstringRule::CaseExact.matches(b"abc", &filter(Some("ab"), &[], Some("bc"))));
+        assert!(!SubstringRule::CaseExact.matches(b"abcd", &filter(None, &["c", "b"], None)));
+        assert!(SubstringRule::TelephoneNumber.matches(b"+1 999 000", &filter(Some("+1999"), &[], None)));
+    }
+
+    #[test]
+    fn test_attribute_types() {
+        let mail = attribute_type(&CIString::new("MAIL"));
+        assert!(mail.matches(b"sample_user@example.com", b"SAMPLE_USER@example.com"));
+        assert_eq!(mail.normalize(b"Sample_User@Example.com"), Some(b"sample_user@example.com".to_vec()));
+        let password = attribute_type(&CIString::new("userPassword"));
+        assert!(!password.matches(b"secret", b"SECRET"));
+    }
+
+    #[test]
+    fn test_generalized_time() {
+        assert_eq!(parse_generalized_time(b"19700101000000Z"), Some(0));
+        assert_eq!(parse_generalized_time(b"197001010001Z"), Some(60_000_000_000));
+        assert_eq!(parse_generalized_time(b"1970010100Z"), Some(0));
+        assert_eq!(parse_generalized_time(b"19700101000000"), None);
+        assert_eq!(parse_generalized_time(b"19701301000000Z"), None);
+
+        // formatting round trips
+        for seconds in [0, 951_782_400, 1_709_210_096, 4_102_444_799] {
+            let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+            let formatted = format_generalized_time(time);
+            assert_eq!(parse_generalized_time(formatted.as_bytes()), Some(i128::from(seconds) * 1_000_000_000), "{formatted}");
+        }
+        assert_eq!(format_generalized_time(std::time::UNIX_EPOCH + std::time::Duration::from_secs(951_782_400)), "20000229000000Z");
+    }
+
+    #[test]
+    fn test_approx() {
+        assert_eq!(soundex("Robert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Rupert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Ashcraft").as_deref(), Some("A261"));
+        assert_eq!(soundex("Tymczak").as_deref(), Some("T522"));
+        assert!(approx_matches(b"Jon Smith", b"john smyth"));
+        assert!(!approx_matches(b"Jon Smith", b"john"));
+        assert!(!approx_matches(b"1000", b"1001"));
+    }
+
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::upper_case_acronyms)]
+
 mod commands;
 mod ldap;
 
@@ -7,6 +9,12 @@ use clap::Parser;
 pub enum Command {
     /// Start the femtoLDAP server
     Server(commands::ServerArgs),
+    /// Hash a password read from stdin, for use in the configuration
+    HashPassword(commands::HashPasswordArgs),
+    /// Check a password read from stdin against the configuration
+    VerifyPassword(commands::VerifyPasswordArgs),
+    /// Check the configuration for problems, exits with an error if any is found
+    Check(commands::CheckArgs),
 }
 
 #[derive(clap::Parser, Debug)]
@@ -59,6 +67,9 @@ fn main() -> anyhow::Result<()> {
     runtime.block_on(async move {
         match args.command {
             Command::Server(server_args) => commands::main_server(server_args).await,
+            Command::HashPassword(hash_password_args) => commands::main_hash_password(hash_password_args).await,
+            Command::VerifyPassword(verify_password_args) => commands::main_verify_password(verify_password_args).await,
+            Command::Check(check_args) => commands::main_check(check_args).await,
         }
     })
 }